@@ -0,0 +1,371 @@
+//  SPDX-FileCopyrightText: Copyright 2023 James M. Putnam (putnamjm.design@gmail.com)
+//  SPDX-License-Identifier: MIT
+
+// command line
+//
+//     rsnitch-rs [--hosts path]... [--config-dir dir] [--settings file]
+//                [--interval secs] [--group group] [--no-gui] [command]
+//
+// options override the environment, RSNITCH_HOSTS and HOME, and
+// the environment overrides the config directory's defaults
+use {
+    crate::{
+        format::Format,
+        settings::Settings,
+        snitch::{
+            discover::Discover,
+            host::{Host, HostsFile, Poll},
+            import::Import,
+            nagios::Nagios,
+            validate::{Severity, Validation},
+        },
+        Environment,
+    },
+    clap::{Parser, Subcommand},
+    serde_json::Value,
+    std::path::{Path, PathBuf},
+};
+
+#[derive(Debug, Parser)]
+#[command(name = "rsnitch-rs", version, about = "sntop-like host monitor")]
+pub struct Cli {
+    #[arg(
+        long,
+        global = true,
+        value_name = "PATH",
+        help = "host file or hosts.d directory, may be repeated [default: $RSNITCH_HOSTS]"
+    )]
+    pub hosts: Vec<PathBuf>,
+    #[arg(
+        long,
+        global = true,
+        value_name = "DIR",
+        help = "config directory [default: $XDG_CONFIG_HOME/rsnitch-rs]"
+    )]
+    pub config_dir: Option<PathBuf>,
+    #[arg(
+        long,
+        global = true,
+        value_name = "FILE",
+        help = "settings file [default: settings.toml or settings.json in the config directory]"
+    )]
+    pub settings: Option<PathBuf>,
+    #[arg(
+        long,
+        value_name = "SECS",
+        value_parser = clap::value_parser!(u64).range(1..),
+        help = "seconds between polls [default: 180]"
+    )]
+    pub interval: Option<u64>,
+    #[arg(long, help = "show only this group to start with")]
+    pub group: Option<String>,
+    #[arg(long, help = "use the terminal ui, even with a display")]
+    pub no_gui: bool,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    #[command(about = "merge another host list into the hosts file")]
+    Import {
+        #[arg(value_parser = ["etc-hosts", "ssh-config", "csv", "sntop", "nmap"])]
+        from: String,
+        path: PathBuf,
+        group: Option<String>,
+        #[arg(long, help = "only show what would be added")]
+        dry_run: bool,
+    },
+    #[command(about = "print an sntoprc as hosts.json")]
+    Sntop { sntoprc: Option<PathBuf> },
+    #[command(about = "ping sweep an ipv4 range and add the hosts that answer")]
+    Discover {
+        cidr: String,
+        #[arg(default_value = Import::DEFAULT_GROUP)]
+        group: String,
+        #[arg(long, help = "only show what would be added")]
+        dry_run: bool,
+    },
+    #[command(about = "convert a hosts or settings file between json, toml and yaml")]
+    Convert { from: PathBuf, to: PathBuf },
+    #[command(about = "check host files, by default all those loaded")]
+    Validate { hosts: Vec<PathBuf> },
+    #[command(about = "check a host or group as a nagios plugin")]
+    Check {
+        #[arg(help = "host name, label, or group")]
+        name: String,
+        #[arg(
+            long,
+            value_name = "MS",
+            help = "rtt warning limit [default: the host's rtt_ms]"
+        )]
+        warn: Option<f64>,
+        #[arg(long, value_name = "MS", help = "rtt critical limit")]
+        critical: Option<f64>,
+    },
+}
+
+impl Cli {
+    // run the subcommand, if there is one, and return its exit status
+    pub fn command(&self, env: &Environment) -> Option<i32> {
+        match self.command.as_ref()? {
+            Command::Import {
+                from,
+                path,
+                group,
+                dry_run,
+            } => Some(Self::import(env, from, path, group.as_deref(), *dry_run)),
+            Command::Sntop { sntoprc } => Some(Self::sntop(env, sntoprc.as_deref())),
+            Command::Discover {
+                cidr,
+                group,
+                dry_run,
+            } => Some(Self::discover(env, cidr, group, *dry_run)),
+            Command::Convert { from, to } => Some(Self::convert(from, to)),
+            Command::Validate { hosts } => Some(Self::validate(env, hosts)),
+            Command::Check {
+                name,
+                warn,
+                critical,
+            } => Some(Self::check(env, name, *warn, *critical)),
+        }
+    }
+
+    fn import(
+        env: &Environment,
+        from: &str,
+        path: &Path,
+        group: Option<&str>,
+        dry_run: bool,
+    ) -> i32 {
+        let import = match Import::from_name(from) {
+            Some(import) => import,
+            None => {
+                eprintln!("{}: not a host list rsnitch-rs can import", from);
+                return 2;
+            }
+        };
+
+        match import.read(path, group) {
+            Ok(hosts) => Self::merge(env, hosts, dry_run),
+            Err(e) => {
+                eprintln!("{}: {}", path.display(), e);
+                1
+            }
+        }
+    }
+
+    // sweeping needs the pinger's raw socket
+    fn discover(env: &Environment, cidr: &str, group: &str, dry_run: bool) -> i32 {
+        sudo::with_env(&Environment::SUDO_ENV).expect("sudo failed");
+
+        let poll = match Poll::new(env) {
+            Ok(poll) => poll,
+            Err(e) => {
+                eprintln!("{}", e);
+                return 1;
+            }
+        };
+
+        match Discover::sweep(&poll, cidr, group) {
+            Ok(hosts) => Self::merge(env, hosts, dry_run),
+            Err(e) => {
+                eprintln!("{}", e);
+                1
+            }
+        }
+    }
+
+    // poll a host or group once and print a nagios status line.
+    // everything goes to stdout, the exit status is the plugin's.
+    // the pinger's raw socket needs root or cap_net_raw, and unlike
+    // the monitor it doesn't escalate through sudo, which can't
+    // prompt under a monitoring server
+    fn check(env: &Environment, name: &str, warn: Option<f64>, critical: Option<f64>) -> i32 {
+        let poll = match Poll::new(env) {
+            Ok(poll) => poll,
+            Err(e) => {
+                println!("RSNITCH UNKNOWN - {}", e);
+                return Nagios::UNKNOWN;
+            }
+        };
+        let hosts = match Host::load(env) {
+            Ok(hosts) => hosts,
+            Err(e) => {
+                println!("RSNITCH UNKNOWN - {}", e);
+                return Nagios::UNKNOWN;
+            }
+        };
+        let ping = env
+            .settings
+            .as_ref()
+            .map(|settings| settings.ping.clone())
+            .unwrap_or_default();
+
+        let (line, code) = Nagios::check(&poll, &ping, &hosts, name, warn, critical);
+
+        println!("{}", line);
+        code
+    }
+
+    // report every problem in the host files, by default all those
+    // loaded, and fail if any are errors
+    fn validate(env: &Environment, hosts: &[PathBuf]) -> i32 {
+        let paths = match hosts.is_empty() {
+            true => Host::files(env),
+            false => hosts.to_vec(),
+        };
+
+        let mut errors = 0;
+
+        for path in paths {
+            let validation = Validation::validate(&path, true);
+
+            println!("{}", validation);
+            errors += validation.count(Severity::Error)
+        }
+
+        match errors {
+            0 => 0,
+            _ => 1,
+        }
+    }
+
+    // convert a hosts file, or a settings file, between formats.
+    // any host list the importers recognize converts too
+    fn convert(from: &Path, to: &Path) -> i32 {
+        let format = match Format::from_path(to) {
+            Some(format) => format,
+            None => {
+                eprintln!("{}: not a .json, .toml, or .yaml file", to.display());
+                return 2;
+            }
+        };
+        let from_format = Format::from_path(from).unwrap_or(Format::Json);
+
+        let text = match Import::from_path(from) {
+            Some(import) => import
+                .read(from, None)
+                .map_err(|e| e.to_string())
+                .and_then(|hosts| Host::serialize(format, &hosts)),
+            None => std::fs::read_to_string(from)
+                .map_err(|e| e.to_string())
+                .and_then(|text| match Self::is_settings(from_format, &text) {
+                    true => from_format
+                        .parse::<Settings>(&text)
+                        .and_then(|settings| format.serialize(&settings)),
+                    false => HostsFile::raw(from_format, &text).and_then(|file| file.text(format)),
+                }),
+        };
+
+        match text.and_then(|text| std::fs::write(to, text).map_err(|e| e.to_string())) {
+            Ok(()) => 0,
+            Err(e) => {
+                eprintln!("{}: {}", from.display(), e);
+                1
+            }
+        }
+    }
+
+    // a hosts file is a list of hosts, or a table with hosts or
+    // include in it. any other table is taken for settings
+    fn is_settings(format: Format, text: &str) -> bool {
+        match format.parse::<Value>(text) {
+            Ok(Value::Object(table)) => {
+                !table.contains_key("hosts") && !table.contains_key("include")
+            }
+            _ => false,
+        }
+    }
+
+    // merge into the hosts file, or with --dry-run, only show
+    // what would be added (+) and what is already present (=).
+    // nothing is merged into host files that didn't load, as every
+    // host would look new
+    fn merge(env: &Environment, imported: Vec<Host>, dry_run: bool) -> i32 {
+        let hosts_path = Host::path(env);
+        let mut hosts = match Host::load(env) {
+            Ok(hosts) => hosts,
+            Err(e) => {
+                eprintln!("{}", e);
+                return 1;
+            }
+        };
+
+        if dry_run {
+            for host in &imported {
+                let present = hosts
+                    .iter()
+                    .any(|present| present.host.eq_ignore_ascii_case(&host.host));
+                let host = host.restored();
+                let probes: Vec<String> =
+                    host.probes.iter().map(|probe| probe.to_string()).collect();
+
+                println!(
+                    "{} {} {} {}",
+                    if present { "=" } else { "+" },
+                    host.host,
+                    host.group,
+                    probes.join(",")
+                );
+            }
+        }
+
+        let imported = imported
+            .into_iter()
+            .map(|host| Host {
+                source: hosts_path.clone(),
+                ..host
+            })
+            .collect();
+        let present = hosts.len();
+        let added = Import::merge(&mut hosts, imported);
+
+        if dry_run {
+            println!("{}: would add {} hosts", hosts_path.display(), added);
+            return 0;
+        }
+
+        match Host::write_back(&hosts[present..]) {
+            Ok(()) => {
+                println!("{}: added {} hosts", hosts_path.display(), added);
+                0
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                1
+            }
+        }
+    }
+
+    // convert an sntoprc to hosts.json on stdout
+    fn sntop(env: &Environment, sntoprc: Option<&Path>) -> i32 {
+        let path = match sntoprc {
+            Some(path) => path.to_path_buf(),
+            None => match Import::sntoprc(&env.home_path) {
+                Some(path) => path,
+                None => {
+                    eprintln!("no ~/.sntoprc or /etc/sntoprc");
+                    return 1;
+                }
+            },
+        };
+
+        match Import::Sntop.read(&path, None) {
+            Ok(hosts) => match serde_json::to_string_pretty(&hosts) {
+                Ok(json) => {
+                    println!("{}", json);
+                    0
+                }
+                Err(e) => {
+                    eprintln!("{}: {}", path.display(), e);
+                    1
+                }
+            },
+            Err(e) => {
+                eprintln!("{}: {}", path.display(), e);
+                1
+            }
+        }
+    }
+}
@@ -0,0 +1,240 @@
+//  SPDX-FileCopyrightText: Copyright 2023 James M. Putnam (putnamjm.design@gmail.com)
+//  SPDX-License-Identifier: MIT
+
+// string interpolation
+//
+// host and settings string fields can take values from the
+// environment, "${VAR}" anywhere in the string, or from a file,
+// "file:path" as the whole string, expanded when they're loaded.
+// "$${" is a literal "${", and "secret:" in front of the whole
+// string marks what follows it as a secret.
+//
+// a struct whose fields are written back keeps the text each of
+// its expanded fields came from, in its Templates, so they can be
+// written back as they were. values read from a file, and those
+// marked secret, are also shown as the text they came from
+use std::path::Path;
+
+// where an expanded field's value came from
+#[derive(Debug, Clone)]
+struct Template {
+    key: String,
+    text: String,
+    value: String,
+    secret: bool,
+}
+
+// the templates of a struct's expanded fields, by field key
+#[derive(Debug, Clone, Default)]
+pub struct Templates {
+    templates: Vec<Template>,
+}
+
+impl Templates {
+    // expand a field in place, remembering the text it came from
+    pub fn field(&mut self, key: &str, field: &mut String) -> Result<(), String> {
+        let (value, secret) = Interpolate::expand_secret(field)?;
+
+        if value != *field {
+            self.templates.push(Template {
+                key: key.to_string(),
+                text: field.clone(),
+                value: value.clone(),
+                secret,
+            });
+
+            *field = value
+        }
+
+        Ok(())
+    }
+
+    // the text a field came from, for writing it back, as long as
+    // it still has the value it was expanded to
+    pub fn restore(&self, key: &str, field: &str) -> String {
+        match self
+            .templates
+            .iter()
+            .find(|template| template.key == key && template.value == field)
+        {
+            Some(template) => template.text.clone(),
+            None => field.to_string(),
+        }
+    }
+
+    // the secret values in text replaced by the text they came from,
+    // where they're whole words, so a short secret isn't replaced
+    // inside other words
+    pub fn redact(&self, text: &str) -> String {
+        self.templates
+            .iter()
+            .filter(|template| template.secret && !template.value.is_empty())
+            .fold(text.to_string(), |text, template| {
+                Self::replace_words(&text, &template.value, &template.text)
+            })
+    }
+
+    fn replace_words(text: &str, word: &str, with: &str) -> String {
+        let in_word = |ch: Option<char>| ch.is_some_and(|ch| ch.is_alphanumeric() || ch == '_');
+        let mut replaced = String::new();
+        let mut from = 0;
+
+        for (at, _) in text.match_indices(word) {
+            let end = at + word.len();
+
+            if at >= from
+                && !in_word(text[..at].chars().next_back())
+                && !in_word(text[end..].chars().next())
+            {
+                replaced.push_str(&text[from..at]);
+                replaced.push_str(with);
+                from = end
+            }
+        }
+
+        replaced.push_str(&text[from..]);
+        replaced
+    }
+}
+
+pub struct Interpolate {}
+
+impl Interpolate {
+    const FILE: &str = "file:";
+    const SECRET: &str = "secret:";
+
+    // expand a field in place, where it won't be written back
+    pub fn field(field: &mut String) -> Result<(), String> {
+        *field = Self::expand(field)?;
+
+        Ok(())
+    }
+
+    pub fn expand(text: &str) -> Result<String, String> {
+        Self::expand_secret(text).map(|(value, _)| value)
+    }
+
+    // the expanded text, and whether it's a secret
+    fn expand_secret(text: &str) -> Result<(String, bool), String> {
+        match text.strip_prefix(Self::SECRET) {
+            Some(text) => Ok((Self::expand_file(text)?, true)),
+            None => Ok((Self::expand_file(text)?, text.starts_with(Self::FILE))),
+        }
+    }
+
+    fn expand_file(text: &str) -> Result<String, String> {
+        match text.strip_prefix(Self::FILE) {
+            Some(path) => {
+                let path = Self::vars(path)?;
+
+                match std::fs::read_to_string(Path::new(&path)) {
+                    Ok(contents) => Ok(contents.trim_end_matches(['\r', '\n']).to_string()),
+                    Err(e) => Err(format!("{}{}: {}", Self::FILE, path, e)),
+                }
+            }
+            None => Self::vars(text),
+        }
+    }
+
+    fn vars(text: &str) -> Result<String, String> {
+        let mut expanded = String::new();
+        let mut rest = text;
+
+        while let Some(start) = rest.find('$') {
+            expanded.push_str(&rest[..start]);
+            rest = &rest[start..];
+
+            if rest.starts_with("$${") {
+                expanded.push_str("${");
+                rest = &rest[3..];
+            } else if let Some(var) = rest.strip_prefix("${") {
+                let end = match var.find('}') {
+                    Some(end) => end,
+                    None => return Err(format!("{}: unterminated ${{", text)),
+                };
+
+                let name = &var[..end];
+                if name.is_empty() {
+                    return Err(format!("{}: empty ${{}}", text));
+                }
+
+                match std::env::var(name) {
+                    Ok(value) => expanded.push_str(&value),
+                    Err(_) => return Err(format!("${{{}}}: not set", name)),
+                }
+                rest = &var[end + 1..];
+            } else {
+                expanded.push('$');
+                rest = &rest[1..];
+            }
+        }
+
+        expanded.push_str(rest);
+        Ok(expanded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expands_vars() {
+        std::env::set_var("RSNITCH_TEST_EXPAND", "ops");
+
+        assert_eq!(
+            Interpolate::expand("${RSNITCH_TEST_EXPAND}@nas.lan").unwrap(),
+            "ops@nas.lan"
+        );
+        assert_eq!(Interpolate::expand("$${HOME} $5").unwrap(), "${HOME} $5");
+        assert!(Interpolate::expand("${RSNITCH_TEST_UNSET}").is_err());
+        assert!(Interpolate::expand("${RSNITCH_TEST_EXPAND").is_err());
+        assert!(Interpolate::expand("${}").is_err());
+    }
+
+    #[test]
+    fn expands_files() {
+        let path = std::env::temp_dir().join("rsnitch-test-expand-file");
+
+        std::fs::write(&path, "hunter2\n").unwrap();
+        assert_eq!(
+            Interpolate::expand(&format!("file:{}", path.display())).unwrap(),
+            "hunter2"
+        );
+        std::fs::remove_file(&path).ok();
+        assert!(Interpolate::expand(&format!("file:{}", path.display())).is_err());
+    }
+
+    #[test]
+    fn restores_expanded_fields() {
+        std::env::set_var("RSNITCH_TEST_RESTORE", "nas.lan");
+
+        let mut templates = Templates::default();
+        let mut host = "${RSNITCH_TEST_RESTORE}".to_string();
+        let mut group = "dc1".to_string();
+
+        templates.field("host", &mut host).unwrap();
+        templates.field("group", &mut group).unwrap();
+        assert_eq!(host, "nas.lan");
+        assert_eq!(templates.restore("host", &host), "${RSNITCH_TEST_RESTORE}");
+        assert_eq!(templates.restore("host", "edited.lan"), "edited.lan");
+        assert_eq!(templates.restore("group", &group), "dc1");
+    }
+
+    #[test]
+    fn redacts_secrets_as_whole_words() {
+        std::env::set_var("RSNITCH_TEST_SECRET", "ab");
+
+        let mut templates = Templates::default();
+        let mut token = "secret:${RSNITCH_TEST_SECRET}".to_string();
+        let mut user = "${RSNITCH_TEST_SECRET}".to_string();
+
+        templates.field("token", &mut token).unwrap();
+        templates.field("user", &mut user).unwrap();
+        assert_eq!(token, "ab");
+        assert_eq!(
+            templates.redact("ab: table abc, ab_1 and (ab)"),
+            "secret:${RSNITCH_TEST_SECRET}: table abc, ab_1 and (secret:${RSNITCH_TEST_SECRET})"
+        );
+    }
+}
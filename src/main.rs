@@ -2,13 +2,20 @@
 //  SPDX-License-Identifier: MIT
 #![allow(dead_code)]
 
+mod cli;
+mod format;
+mod interpolate;
 mod settings;
 mod snitch;
+mod textui;
 
 use {
+    clap::Parser,
+    cli::Cli,
     iced::{window, Application, Settings},
     serde::{Deserialize, Serialize},
     snitch::snitch_ui::SnitchUi,
+    textui::text_ui::TextUi,
 };
 
 #[derive(Default, Serialize, Deserialize)]
@@ -17,55 +24,111 @@ pub struct Environment {
     hostname: String,
     home_path: std::path::PathBuf,
     config_path: std::path::PathBuf,
-    hosts_path: Option<std::path::PathBuf>,
+    state_path: std::path::PathBuf,
+    cache_path: std::path::PathBuf,
+    hosts_paths: Vec<std::path::PathBuf>,
+    settings_path: Option<std::path::PathBuf>,
     settings: Option<settings::Settings>,
+    interval: Option<u64>,
+    group: Option<String>,
 }
 
 impl Environment {
-    const CONFIG_PATH: &str = ".config/rsnitch-rs";
-    const SETTINGS_FILE: &str = "settings.json";
+    const NAME: &str = "rsnitch-rs";
+    const SYSTEM_PATH: &str = "/etc/rsnitch-rs";
+    const SETTINGS_FILES: [&str; 2] = ["settings.toml", "settings.json"];
+    // kept across sudo, which the pinger's raw socket needs
+    const SUDO_ENV: [&str; 7] = [
+        "HOME",
+        "USER",
+        "RSNITCH_HOSTS",
+        "XDG_CACHE_HOME",
+        "XDG_CONFIG_HOME",
+        "XDG_RUNTIME_DIR",
+        "XDG_STATE_HOME",
+    ];
 
-    fn dotfiles(self) -> Self {
-        let config_path = self.config_path.as_path();
+    // $XDG_CONFIG_HOME and friends, else their defaults under the
+    // home directory. the spec ignores relative paths
+    fn xdg(home_path: &std::path::Path, var: &str, default: &str) -> std::path::PathBuf {
+        let base = std::path::PathBuf::from(envmnt::get_or(var, ""));
 
-        if !config_path.exists() {
-            std::fs::create_dir(config_path).unwrap();
+        match base.is_absolute() {
+            true => base.join(Self::NAME),
+            false => home_path.join(default).join(Self::NAME),
         }
+    }
 
-        let settings = settings::Settings::from_env(&self);
+    // the command line's options, else the environment's, else
+    // the defaults under the home directory
+    fn new(cli: &Cli) -> Self {
+        let home = &envmnt::get_or("HOME", "");
+        let home_path = std::path::Path::new(home);
+
+        // RSNITCH_HOSTS is a list of host files and hosts.d style
+        // directories, separated like PATH
+        let hosts_paths = if cli.hosts.is_empty() {
+            std::env::split_paths(&envmnt::get_or("RSNITCH_HOSTS", ""))
+                .filter(|path| !path.as_os_str().is_empty())
+                .collect()
+        } else {
+            cli.hosts.clone()
+        };
 
         Environment {
-            user: self.user,
-            hostname: self.hostname,
-            home_path: self.home_path,
-            config_path: self.config_path,
-            hosts_path: self.hosts_path,
-            settings,
+            user: whoami::username(),
+            hostname: whoami::hostname(),
+            home_path: home_path.to_path_buf(),
+            config_path: match &cli.config_dir {
+                Some(config_dir) => config_dir.clone(),
+                None => Self::xdg(home_path, "XDG_CONFIG_HOME", ".config"),
+            },
+            state_path: Self::xdg(home_path, "XDG_STATE_HOME", ".local/state"),
+            cache_path: Self::xdg(home_path, "XDG_CACHE_HOME", ".cache"),
+            hosts_paths,
+            settings_path: cli.settings.clone(),
+            settings: None,
+            interval: cli.interval,
+            group: cli.group.clone(),
         }
     }
+
+    fn dotfiles(self) -> Self {
+        for path in [&self.config_path, &self.state_path, &self.cache_path] {
+            if let Err(e) = std::fs::create_dir_all(path) {
+                eprintln!("{}: {}", path.display(), e);
+            }
+        }
+
+        let settings = settings::Settings::from_env(&self);
+
+        Environment { settings, ..self }
+    }
 }
 
 pub fn main() -> iced::Result {
-    let hosts_path = &envmnt::get_or("RSNITCH_HOSTS", "");
-    let home = &envmnt::get_or("HOME", "");
-    let home_path = std::path::Path::new(home);
-    let config_path = std::path::Path::new(Environment::CONFIG_PATH);
-
-    let env = Environment {
-        user: whoami::username(),
-        hostname: whoami::hostname(),
-        home_path: home_path.to_path_buf(),
-        config_path: std::path::Path::join(home_path, config_path),
-        hosts_path: if hosts_path.is_empty() {
-            None
-        } else {
-            Some(std::path::Path::new(hosts_path).to_path_buf())
-        },
-        settings: None,
+    let cli = Cli::parse();
+    let env = Environment::new(&cli).dotfiles();
+
+    if let Some(status) = cli.command(&env) {
+        std::process::exit(status);
     }
-    .dotfiles();
 
-    sudo::with_env(&["HOME", "USER", "RSNITCH_HOSTS", "XDG_RUNTIME_DIR"]).expect("sudo failed");
+    // without a display, say over ssh, fall back to the terminal ui
+    let no_gui = cli.no_gui
+        || (envmnt::get_or("DISPLAY", "").is_empty()
+            && envmnt::get_or("WAYLAND_DISPLAY", "").is_empty());
+
+    sudo::with_env(&Environment::SUDO_ENV).expect("sudo failed");
+
+    if no_gui {
+        if let Err(e) = TextUi::run(&env) {
+            eprintln!("rsnitch-rs: {}", e);
+            std::process::exit(1);
+        }
+
+        return Ok(());
+    }
 
     SnitchUi::run(Settings {
         exit_on_close_request: true,
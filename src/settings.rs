@@ -3,15 +3,29 @@
 #![allow(dead_code)]
 #![allow(unused_imports)]
 use {
-    crate::{snitch, Environment},
+    crate::{
+        format::Format,
+        interpolate::Interpolate,
+        snitch::{self, alert::Alert, maintenance::Maintenance},
+        Environment,
+    },
     serde::{Deserialize, Serialize},
+    std::{fs::File, io::BufReader, path::Path},
     //     textui::text_ui::TextUi as TextUi_,
 };
 
 #[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Settings {
     window: Window,
     textui: TextUi,
+    pub keys: Keys,
+    pub theme: Theme,
+    pub alerts: Vec<Alert>,
+    pub ping: Ping,
+    pub hysteresis: Hysteresis,
+    pub maintenance: Vec<Maintenance>,
+    pub traceroute: Traceroute,
 }
 
 #[derive(Default, Serialize, Deserialize)]
@@ -28,29 +42,232 @@ struct TextUi {
     cursor: Option<usize>,
 }
 
+// key bindings, by key name: a letter or digit, "/", or one of
+// "up", "down", "left", "right", "enter", "escape"
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Keys {
+    pub up: String,
+    pub down: String,
+    pub left: String,
+    pub right: String,
+    pub details: String,
+    pub refresh: String,
+    pub search: String,
+    pub clear: String,
+    pub silence: String,
+    pub trace: String,
+    pub groups: Vec<String>,
+}
+
+impl Default for Keys {
+    fn default() -> Self {
+        Keys {
+            up: "up".to_string(),
+            down: "down".to_string(),
+            left: "left".to_string(),
+            right: "right".to_string(),
+            details: "enter".to_string(),
+            refresh: "r".to_string(),
+            search: "/".to_string(),
+            clear: "escape".to_string(),
+            silence: "s".to_string(),
+            trace: "t".to_string(),
+            groups: (1..10).map(|n| n.to_string()).collect(),
+        }
+    }
+}
+
+// pings sent to each host per poll, and the percentages of them
+// lost at which a host is degraded and down
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Ping {
+    pub count: usize,
+    pub degraded_loss: f64,
+    pub down_loss: f64,
+}
+
+impl Default for Ping {
+    fn default() -> Self {
+        Ping {
+            count: 3,
+            degraded_loss: 20.0,
+            down_loss: 100.0,
+        }
+    }
+}
+
+// polls in a row a new state needs before it's shown, and the
+// flapping scores at which a host starts and stops flapping
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Hysteresis {
+    pub down_after: usize,
+    pub up_after: usize,
+    pub flap_window: usize,
+    pub flap_high: f64,
+    pub flap_low: f64,
+}
+
+impl Default for Hysteresis {
+    fn default() -> Self {
+        Hysteresis {
+            down_after: 2,
+            up_after: 1,
+            flap_window: 20,
+            flap_high: 50.0,
+            flap_low: 25.0,
+        }
+    }
+}
+
+// method is "icmp" or "udp"
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Traceroute {
+    pub method: String,
+    pub max_hops: u8,
+}
+
+impl Default for Traceroute {
+    fn default() -> Self {
+        Traceroute {
+            method: "icmp".to_string(),
+            max_hops: 30,
+        }
+    }
+}
+
+// colors are "#rrggbb". preset is "light", "dark", or "custom",
+// which builds the theme from palette
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Theme {
+    pub preset: String,
+    pub palette: Option<Palette>,
+    pub states: StateColors,
+}
+
+// colors left out are the light preset's
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Palette {
+    pub background: String,
+    pub text: String,
+    pub primary: String,
+    pub success: String,
+    pub danger: String,
+}
+
+// the defaults are from the Okabe-Ito colour-blind safe palette
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StateColors {
+    pub up: String,
+    pub down: String,
+    pub degraded: String,
+    pub unreachable: String,
+    pub unknown: String,
+}
+
+impl Default for StateColors {
+    fn default() -> Self {
+        StateColors {
+            up: "#0072b2".to_string(),
+            down: "#d55e00".to_string(),
+            degraded: "#e69f00".to_string(),
+            unreachable: "#cc79a7".to_string(),
+            unknown: "#999999".to_string(),
+        }
+    }
+}
+
 impl Settings {
-    const DEFAULT: Settings = Settings {
-        window: Window {
-            size: None,
-            min_size: None,
-            max_size: None,
-            resizable: None,
-        },
-        textui: TextUi {
-            rows: None,
-            cursor: None,
-        },
-    };
+    // expand ${VAR} and file: in the string fields
+    pub fn interpolate(&mut self) -> Result<(), String> {
+        let keys = &mut self.keys;
+        let theme = &mut self.theme;
+        let states = &mut theme.states;
+
+        let mut fields = vec![
+            &mut keys.up,
+            &mut keys.down,
+            &mut keys.left,
+            &mut keys.right,
+            &mut keys.details,
+            &mut keys.refresh,
+            &mut keys.search,
+            &mut keys.clear,
+            &mut keys.silence,
+            &mut keys.trace,
+            &mut self.traceroute.method,
+            &mut theme.preset,
+            &mut states.up,
+            &mut states.down,
+            &mut states.degraded,
+            &mut states.unreachable,
+            &mut states.unknown,
+        ];
+        fields.extend(keys.groups.iter_mut());
 
+        if let Some(palette) = &mut theme.palette {
+            fields.extend([
+                &mut palette.background,
+                &mut palette.text,
+                &mut palette.primary,
+                &mut palette.success,
+                &mut palette.danger,
+            ])
+        }
+
+        for field in fields {
+            Interpolate::field(field)?
+        }
+
+        for alert in &mut self.alerts {
+            alert.interpolate()?
+        }
+
+        for maintenance in &mut self.maintenance {
+            maintenance.interpolate()?
+        }
+
+        Ok(())
+    }
+
+    // the settings file given, else settings.toml, else settings.json,
+    // in the config directory
     pub fn from_env(env: &Environment) -> Option<Self> {
         let dot_path = env.config_path.as_path();
 
         if dot_path.exists() {
-            // let settings = std::path::Path::new(Environment::SETTINGS_FILE);
-            // let _settings_path = std::path::Path::join(dot_path, settings);
-            // let _serialized = serde_json::to_string(&Settings {}).unwrap();
+            let settings_path = match &env.settings_path {
+                Some(path) => Some(path.clone()),
+                None => Environment::SETTINGS_FILES
+                    .iter()
+                    .map(|file| Path::join(dot_path, file))
+                    .find(|path| path.exists()),
+            };
+
+            match settings_path {
+                Some(path) => {
+                    let format = Format::from_path(&path).unwrap_or(Format::Json);
 
-            Some(Settings::default())
+                    match std::fs::read_to_string(&path)
+                        .map_err(|e| e.to_string())
+                        .and_then(|text| format.parse::<Settings>(&text))
+                        .and_then(|mut settings| settings.interpolate().map(|_| settings))
+                    {
+                        Ok(settings) => Some(settings),
+                        Err(e) => {
+                            eprintln!("{}: {}", path.display(), e);
+                            Some(Settings::default())
+                        }
+                    }
+                }
+                None => Some(Settings::default()),
+            }
         } else {
             None
         }
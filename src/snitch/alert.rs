@@ -0,0 +1,126 @@
+//  SPDX-FileCopyrightText: Copyright 2023 James M. Putnam (putnamjm.design@gmail.com)
+//  SPDX-License-Identifier: MIT
+
+// alert rules
+//
+// rules are listed in settings, and run a command when a host
+// changes into one of the rule's states
+//
+//     "alerts": [
+//         { "on": ["degraded", "down"], "group": "dc1", "command": ["notify-send", "rsnitch"] }
+//     ]
+//
+// the command is run without a shell, with the host in the
+// environment as RSNITCH_HOST, RSNITCH_LABEL, RSNITCH_GROUP,
+// RSNITCH_STATE and RSNITCH_PREVIOUS. a rule without a group
+// applies to every host, and flapping hosts are left out
+use {
+    super::{
+        group::Group,
+        host::{Host, State},
+    },
+    crate::{interpolate::Templates, Environment},
+    serde::{Deserialize, Serialize},
+    std::process::{Command, Stdio},
+};
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Alert {
+    pub on: Vec<State>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    pub command: Vec<String>,
+    #[serde(skip)]
+    pub templates: Templates,
+}
+
+impl Alert {
+    // expand ${VAR} and file: in the group and command
+    pub fn interpolate(&mut self) -> Result<(), String> {
+        if let Some(group) = &mut self.group {
+            self.templates.field("group", group)?
+        }
+
+        for (n, field) in self.command.iter_mut().enumerate() {
+            self.templates.field(&format!("command.{}", n), field)?
+        }
+
+        Ok(())
+    }
+
+    fn applies(&self, host: &Host, state: State) -> bool {
+        self.on.contains(&state)
+            && match &self.group {
+                Some(group) => Group::contains(group, &host.group),
+                None => true,
+            }
+    }
+
+    fn run(&self, host: &Host, last: State, state: State) -> std::io::Result<()> {
+        let (program, args) = match self.command.split_first() {
+            Some(command) => command,
+            None => return Ok(()),
+        };
+
+        let mut child = Command::new(program)
+            .args(args)
+            .env("RSNITCH_HOST", &host.host)
+            .env("RSNITCH_LABEL", &host.label)
+            .env("RSNITCH_GROUP", &host.group)
+            .env("RSNITCH_STATE", state.as_str())
+            .env("RSNITCH_PREVIOUS", last.as_str())
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .spawn()?;
+
+        // reap the command without waiting on it
+        std::thread::spawn(move || child.wait());
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Alerts {
+    alerts: Vec<Alert>,
+}
+
+impl Alerts {
+    pub fn new(env: &Environment) -> Self {
+        Alerts {
+            alerts: match &env.settings {
+                Some(settings) => settings.alerts.clone(),
+                None => Vec::new(),
+            },
+        }
+    }
+
+    // run the rules for the hosts whose state changed between two
+    // polls of the same hosts, as History::record compares them,
+    // except for the quiet ones
+    pub fn fire(&self, hosts: &[Host], last: &[State], states: &[State], quiet: &[bool]) {
+        if last.len() != states.len() || hosts.len() != states.len() {
+            return;
+        }
+
+        for (id, host) in hosts.iter().enumerate() {
+            if last[id] == states[id] || quiet.get(id) == Some(&true) {
+                continue;
+            }
+
+            for alert in self
+                .alerts
+                .iter()
+                .filter(|alert| alert.applies(host, states[id]))
+            {
+                if let Err(e) = alert.run(host, last[id], states[id]) {
+                    eprintln!(
+                        "alert {}: {}",
+                        alert.templates.redact(&alert.command.join(" ")),
+                        e
+                    )
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,133 @@
+//  SPDX-FileCopyrightText: Copyright 2023 James M. Putnam (putnamjm.design@gmail.com)
+//  SPDX-License-Identifier: MIT
+
+// ping bursts
+//
+// each poll sends a burst of pings to every address, count of
+// them from the ping section of settings, and a host's state is
+// decided by how many were lost rather than by a single packet
+//
+//     "ping": { "count": 5, "degraded_loss": 20, "down_loss": 100 }
+//
+// jitter is the mean difference between successive round trips
+use {super::host::State, std::time::Duration};
+
+#[derive(Debug, Default, Clone)]
+pub struct Burst {
+    pub sent: usize,
+    // the round trips of the replies, in the order they arrived
+    pub rtts: Vec<Duration>,
+}
+
+impl Burst {
+    pub fn received(&self) -> usize {
+        self.rtts.len()
+    }
+
+    pub fn loss_pct(&self) -> f64 {
+        match self.sent {
+            0 => 100.0,
+            sent => (sent - self.received()) as f64 * 100.0 / sent as f64,
+        }
+    }
+
+    pub fn min(&self) -> Option<Duration> {
+        self.rtts.iter().min().copied()
+    }
+
+    pub fn max(&self) -> Option<Duration> {
+        self.rtts.iter().max().copied()
+    }
+
+    pub fn avg(&self) -> Option<Duration> {
+        match self.received() {
+            0 => None,
+            received => Some(self.rtts.iter().sum::<Duration>() / received as u32),
+        }
+    }
+
+    pub fn jitter(&self) -> Option<Duration> {
+        match self.received() {
+            0 | 1 => None,
+            received => Some(
+                self.rtts
+                    .windows(2)
+                    .map(|pair| pair[0].max(pair[1]) - pair[0].min(pair[1]))
+                    .sum::<Duration>()
+                    / (received - 1) as u32,
+            ),
+        }
+    }
+
+    // down when nothing comes back or the loss reaches down_loss,
+    // degraded when it reaches degraded_loss
+    pub fn state(&self, degraded_loss: f64, down_loss: f64) -> State {
+        let loss = self.loss_pct();
+
+        if self.received() == 0 || loss >= down_loss {
+            State::Down
+        } else if loss >= degraded_loss {
+            State::Degraded
+        } else {
+            State::Up
+        }
+    }
+
+    // the line for the host detail panel
+    pub fn line(&self) -> String {
+        let millis =
+            |rtt: Option<Duration>| rtt.map(|rtt| rtt.as_secs_f64() * 1000.0).unwrap_or(0.0);
+
+        format!(
+            "rtt min/avg/max: {:.1}/{:.1}/{:.1} ms, jitter {:.1} ms, loss {:.0}% of {}",
+            millis(self.min()),
+            millis(self.avg()),
+            millis(self.max()),
+            millis(self.jitter()),
+            self.loss_pct(),
+            self.sent
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn burst(sent: usize, millis: &[u64]) -> Burst {
+        Burst {
+            sent,
+            rtts: millis.iter().map(|ms| Duration::from_millis(*ms)).collect(),
+        }
+    }
+
+    #[test]
+    fn stats() {
+        let burst = burst(5, &[10, 30, 20, 20]);
+
+        assert_eq!(burst.loss_pct(), 20.0);
+        assert_eq!(burst.min(), Some(Duration::from_millis(10)));
+        assert_eq!(burst.max(), Some(Duration::from_millis(30)));
+        assert_eq!(burst.avg(), Some(Duration::from_millis(20)));
+        // |30-10| + |20-30| + |20-20| over 3
+        assert_eq!(burst.jitter(), Some(Duration::from_millis(10)));
+    }
+
+    #[test]
+    fn no_replies() {
+        let burst = burst(5, &[]);
+
+        assert_eq!(burst.loss_pct(), 100.0);
+        assert_eq!(burst.avg(), None);
+        assert_eq!(burst.jitter(), None);
+        assert_eq!(Burst::default().loss_pct(), 100.0);
+    }
+
+    #[test]
+    fn state_by_loss() {
+        assert_eq!(burst(5, &[1, 1, 1, 1, 1]).state(20.0, 100.0), State::Up);
+        assert_eq!(burst(5, &[1, 1, 1, 1]).state(20.0, 100.0), State::Degraded);
+        assert_eq!(burst(5, &[1, 1]).state(20.0, 60.0), State::Down);
+        assert_eq!(burst(5, &[]).state(20.0, 200.0), State::Down);
+    }
+}
@@ -0,0 +1,203 @@
+//  SPDX-FileCopyrightText: Copyright 2023 James M. Putnam (putnamjm.design@gmail.com)
+//  SPDX-License-Identifier: MIT
+
+// host dependencies
+//
+// a host can list the hosts it's reached through, by their host
+// names, in hosts.json
+//
+//     { "group": "dc1", "host": "db1.dc1", "label": "db1", "depends_on": ["core.dc1"] }
+//
+// a host that is down while all of its parents are down, or
+// themselves unreachable, is shown unreachable rather than down,
+// and its alerts aren't run. hosts that depend on each other in a
+// cycle are rejected when the hosts are loaded
+use super::host::{Host, State};
+
+pub struct Depend {}
+
+impl Depend {
+    // the first cycle in a list of names and the names they depend
+    // on, from and back to the same name
+    pub fn cycle(deps: &[(String, Vec<String>)]) -> Option<Vec<String>> {
+        // 0 unvisited, 1 on the current path, 2 done
+        let mut marks = vec![0_u8; deps.len()];
+        let mut path = Vec::new();
+
+        (0..deps.len()).find_map(|id| match marks[id] {
+            0 => Self::visit(id, deps, &mut marks, &mut path),
+            _ => None,
+        })
+    }
+
+    // depth first from id, along the path so far
+    fn visit(
+        id: usize,
+        deps: &[(String, Vec<String>)],
+        marks: &mut [u8],
+        path: &mut Vec<usize>,
+    ) -> Option<Vec<String>> {
+        marks[id] = 1;
+        path.push(id);
+
+        for parent in &deps[id].1 {
+            let parent = match deps.iter().position(|(name, _)| name == parent) {
+                Some(parent) => parent,
+                None => continue,
+            };
+
+            match marks[parent] {
+                0 => {
+                    if let Some(cycle) = Self::visit(parent, deps, marks, path) {
+                        return Some(cycle);
+                    }
+                }
+                1 => {
+                    let start = path.iter().position(|id| *id == parent).unwrap_or(0);
+
+                    return Some(
+                        path[start..]
+                            .iter()
+                            .chain([parent].iter())
+                            .map(|id| deps[*id].0.clone())
+                            .collect(),
+                    );
+                }
+                _ => (),
+            }
+        }
+
+        path.pop();
+        marks[id] = 2;
+        None
+    }
+
+    pub fn host_cycle(hosts: &[Host]) -> Option<Vec<String>> {
+        let deps: Vec<(String, Vec<String>)> = hosts
+            .iter()
+            .map(|host| (host.host.clone(), host.depends_on.clone()))
+            .collect();
+
+        Self::cycle(&deps)
+    }
+
+    // the parents of a host that are in the hosts list
+    pub fn parents(host: &Host, hosts: &[Host]) -> Vec<usize> {
+        host.depends_on
+            .iter()
+            .filter_map(|name| hosts.iter().position(|parent| &parent.host == name))
+            .collect()
+    }
+
+    // down hosts whose parents are all down are unreachable. a
+    // parent that is itself unreachable was down to begin with
+    pub fn apply(hosts: &[Host], states: &[State]) -> Vec<State> {
+        hosts
+            .iter()
+            .zip(states)
+            .map(|(host, state)| {
+                let parents = Self::parents(host, hosts);
+
+                match *state == State::Down
+                    && !parents.is_empty()
+                    && parents
+                        .iter()
+                        .all(|parent| matches!(states[*parent], State::Down | State::Unreachable))
+                {
+                    true => State::Unreachable,
+                    false => *state,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn deps(deps: &[(&str, &[&str])]) -> Vec<(String, Vec<String>)> {
+        deps.iter()
+            .map(|(name, parents)| {
+                (
+                    name.to_string(),
+                    parents.iter().map(|parent| parent.to_string()).collect(),
+                )
+            })
+            .collect()
+    }
+
+    fn host(name: &str, parents: &[&str]) -> Host {
+        Host {
+            host: name.to_string(),
+            depends_on: parents.iter().map(|parent| parent.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn finds_a_cycle() {
+        let deps = deps(&[("a", &["b"]), ("b", &["c"]), ("c", &["a"]), ("d", &[])]);
+
+        assert_eq!(
+            Depend::cycle(&deps),
+            Some(vec![
+                "a".to_string(),
+                "b".to_string(),
+                "c".to_string(),
+                "a".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn finds_a_cycle_off_the_first_path() {
+        let deps = deps(&[("a", &["b"]), ("b", &[]), ("c", &["d"]), ("d", &["d"])]);
+
+        assert_eq!(
+            Depend::cycle(&deps),
+            Some(vec!["d".to_string(), "d".to_string()])
+        );
+    }
+
+    #[test]
+    fn a_diamond_is_not_a_cycle() {
+        let deps = deps(&[
+            ("a", &["b", "c"]),
+            ("b", &["d"]),
+            ("c", &["d"]),
+            ("d", &["elsewhere"]),
+        ]);
+
+        assert_eq!(Depend::cycle(&deps), None);
+    }
+
+    #[test]
+    fn down_behind_down_parents_is_unreachable() {
+        let hosts = [
+            host("core", &[]),
+            host("edge", &["core"]),
+            host("db", &["edge"]),
+            host("web", &["edge", "backup"]),
+            host("backup", &[]),
+        ];
+        let states = [
+            State::Down,
+            State::Down,
+            State::Down,
+            State::Down,
+            State::Up,
+        ];
+
+        assert_eq!(
+            Depend::apply(&hosts, &states),
+            [
+                State::Down,
+                State::Unreachable,
+                State::Unreachable,
+                State::Down,
+                State::Up
+            ]
+        );
+    }
+}
@@ -0,0 +1,120 @@
+//  SPDX-FileCopyrightText: Copyright 2023 James M. Putnam (putnamjm.design@gmail.com)
+//  SPDX-License-Identifier: MIT
+
+// subnet discovery
+//
+// ping every address in an ipv4 cidr range in one batch, and
+// name the ones that answer by reverse dns
+use {
+    super::host::{Host, Poll},
+    dns_lookup::lookup_addr,
+    std::net::{IpAddr, Ipv4Addr},
+};
+
+pub struct Discover {}
+
+impl Discover {
+    // a /16 is 65534 addresses, plenty for one sweep
+    const MIN_PREFIX: u32 = 16;
+
+    // the host addresses of a.b.c.d/n, less the network and
+    // broadcast addresses when the range has them
+    pub fn cidr(cidr: &str) -> Result<Vec<IpAddr>, String> {
+        let (addr, prefix) = match cidr.split_once('/') {
+            Some((addr, prefix)) => (addr, prefix),
+            None => (cidr, "32"),
+        };
+
+        let addr: Ipv4Addr = match addr.parse() {
+            Ok(addr) => addr,
+            Err(_) => return Err(format!("{}: not an ipv4 address", addr)),
+        };
+
+        let prefix: u32 = match prefix.parse() {
+            Ok(prefix) if (Self::MIN_PREFIX..=32).contains(&prefix) => prefix,
+            _ => {
+                return Err(format!(
+                    "{}: prefix must be /{} to /32",
+                    cidr,
+                    Self::MIN_PREFIX
+                ))
+            }
+        };
+
+        let mask = u32::MAX << (32 - prefix);
+        let network = u32::from(addr) & mask;
+        let broadcast = network | !mask;
+
+        let range = if prefix < 31 {
+            network + 1..=broadcast - 1
+        } else {
+            network..=broadcast
+        };
+
+        Ok(range.map(|addr| IpAddr::V4(Ipv4Addr::from(addr))).collect())
+    }
+
+    // candidate hosts for the live addresses, in address order
+    pub fn sweep(poll: &Poll, cidr: &str, group: &str) -> Result<Vec<Host>, String> {
+        let mut alive = poll.sweep(&Self::cidr(cidr)?)?;
+
+        alive.sort();
+
+        Ok(alive
+            .iter()
+            .map(|addr| {
+                let (host, label) = match lookup_addr(addr) {
+                    Ok(name) if name != addr.to_string() => {
+                        let label = name.split('.').next().unwrap_or(&name).to_string();
+
+                        (name, label)
+                    }
+                    _ => (addr.to_string(), addr.to_string()),
+                };
+
+                Host {
+                    group: group.to_string(),
+                    host,
+                    label,
+                    ..Default::default()
+                }
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addrs(cidr: &str) -> Vec<String> {
+        Discover::cidr(cidr)
+            .unwrap()
+            .iter()
+            .map(|addr| addr.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn cidr_leaves_out_network_and_broadcast() {
+        assert_eq!(addrs("192.168.1.77/30"), ["192.168.1.77", "192.168.1.78"]);
+        assert_eq!(Discover::cidr("10.0.0.0/24").unwrap().len(), 254);
+        assert_eq!(Discover::cidr("10.0.0.0/16").unwrap().len(), 65534);
+    }
+
+    #[test]
+    fn cidr_keeps_every_address_of_31_and_32() {
+        assert_eq!(addrs("10.0.0.1/31"), ["10.0.0.0", "10.0.0.1"]);
+        assert_eq!(addrs("10.0.0.9/32"), ["10.0.0.9"]);
+        assert_eq!(addrs("10.0.0.9"), ["10.0.0.9"]);
+    }
+
+    #[test]
+    fn cidr_rejects_bad_ranges() {
+        assert!(Discover::cidr("10.0.0.0/15").is_err());
+        assert!(Discover::cidr("10.0.0.0/33").is_err());
+        assert!(Discover::cidr("10.0.0.0/x").is_err());
+        assert!(Discover::cidr("nas.lan/24").is_err());
+        assert!(Discover::cidr("::1/128").is_err());
+    }
+}
@@ -0,0 +1,205 @@
+//  SPDX-FileCopyrightText: Copyright 2023 James M. Putnam (putnamjm.design@gmail.com)
+//  SPDX-License-Identifier: MIT
+
+// dns resolution
+//
+// host names are resolved through a cache that keeps each answer
+// for its ttl, saved as dns.json in the cache directory between
+// runs. when a name stops resolving its last address is still
+// pinged and the name is marked failing, so a dns outage isn't
+// taken for the host being down. a name the resolver can't answer
+// is looked up through the system's getaddrinfo, which also has
+// nsswitch sources like mdns and systemd-resolved
+use {
+    crate::Environment,
+    serde::{Deserialize, Serialize},
+    std::{
+        collections::{HashMap, HashSet},
+        net::{IpAddr, ToSocketAddrs},
+        path::PathBuf,
+        sync::RwLock,
+        time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+    },
+    trust_dns_resolver::{
+        config::{NameServerConfigGroup, ResolverConfig, ResolverOpts},
+        proto::rr::RecordType,
+        Resolver,
+    },
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Resolution {
+    pub addr: IpAddr,
+    // unix seconds
+    pub expires: u64,
+    #[serde(skip)]
+    pub failing: bool,
+}
+
+// a query's answers and how long it took
+#[derive(Debug, Clone)]
+pub struct Answer {
+    pub records: Vec<String>,
+    pub elapsed: Duration,
+}
+
+pub struct Dns {
+    cache: RwLock<HashMap<String, Resolution>>,
+    // names whose last lookup failed with no address to fall back on
+    failed: RwLock<HashSet<String>>,
+    path: PathBuf,
+    resolver: Option<Resolver>,
+}
+
+impl Dns {
+    const FILE: &str = "dns.json";
+    pub const TIMEOUT: Duration = Duration::from_secs(2);
+    // getaddrinfo doesn't give a ttl
+    const SYSTEM_TTL: Duration = Duration::from_secs(60);
+
+    pub fn new(env: &Environment) -> Self {
+        let path = env.cache_path.join(Self::FILE);
+        let cache = match std::fs::read_to_string(&path) {
+            Ok(json) => serde_json::from_str(&json).unwrap_or_default(),
+            Err(_) => HashMap::new(),
+        };
+
+        Dns {
+            cache: RwLock::new(cache),
+            failed: RwLock::new(HashSet::new()),
+            path,
+            resolver: Resolver::from_system_conf().ok(),
+        }
+    }
+
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|now| now.as_secs())
+            .unwrap_or(0)
+    }
+
+    // the cached address while its ttl lasts, else a fresh lookup,
+    // else the last address known
+    pub fn resolve(&self, name: &str) -> Option<IpAddr> {
+        if let Ok(addr) = name.parse::<IpAddr>() {
+            return Some(addr);
+        }
+
+        if let Some(resolution) = self.cache.read().unwrap().get(name) {
+            if resolution.expires > Self::now() && !resolution.failing {
+                return Some(resolution.addr);
+            }
+        }
+
+        let lookup = self
+            .resolver
+            .as_ref()
+            .and_then(|resolver| resolver.lookup_ip(name).ok())
+            .and_then(|lookup| Some((lookup.iter().next()?, lookup.valid_until())))
+            .or_else(|| {
+                let addr = (name, 0).to_socket_addrs().ok()?.next()?;
+
+                Some((addr.ip(), Instant::now() + Self::SYSTEM_TTL))
+            });
+
+        let mut cache = self.cache.write().unwrap();
+        let mut failed = self.failed.write().unwrap();
+
+        match lookup {
+            Some((addr, valid_until)) => {
+                let ttl = valid_until.saturating_duration_since(Instant::now());
+
+                failed.remove(name);
+
+                cache.insert(
+                    name.to_string(),
+                    Resolution {
+                        addr,
+                        expires: Self::now() + ttl.as_secs(),
+                        failing: false,
+                    },
+                );
+
+                Some(addr)
+            }
+            None => match cache.get_mut(name) {
+                Some(resolution) => {
+                    resolution.failing = true;
+
+                    Some(resolution.addr)
+                }
+                None => {
+                    failed.insert(name.to_string());
+
+                    None
+                }
+            },
+        }
+    }
+
+    // names whose last lookup failed, cached or not. names not
+    // looked up yet, and addresses, aren't failing
+    pub fn failing(&self, name: &str) -> bool {
+        match self.cache.read().unwrap().get(name) {
+            Some(resolution) => resolution.failing,
+            None => self.failed.read().unwrap().contains(name),
+        }
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let cache = self.cache.read().unwrap();
+
+        match serde_json::to_string(&*cache) {
+            Ok(json) => std::fs::write(&self.path, json),
+            Err(e) => Err(std::io::Error::new(std::io::ErrorKind::InvalidData, e)),
+        }
+    }
+
+    // ask a resolver, the system's without one, for a name's records
+    // of a type, uncached, and time it. names and answers are
+    // compared without the trailing dot
+    pub fn query(resolver: Option<IpAddr>, name: &str, record: &str) -> Result<Answer, String> {
+        let record_type: RecordType = record
+            .to_uppercase()
+            .parse()
+            .map_err(|_| format!("{}: unknown record type", record))?;
+
+        let mut opts = ResolverOpts::default();
+        opts.timeout = Self::TIMEOUT;
+        opts.attempts = 1;
+        opts.cache_size = 0;
+
+        let resolver = match resolver {
+            Some(addr) => Resolver::new(
+                ResolverConfig::from_parts(
+                    None,
+                    Vec::new(),
+                    NameServerConfigGroup::from_ips_clear(&[addr], 53, true),
+                ),
+                opts,
+            ),
+            None => trust_dns_resolver::system_conf::read_system_conf()
+                .and_then(|(config, _)| Resolver::new(config, opts)),
+        }
+        .map_err(|e| e.to_string())?;
+
+        let start = Instant::now();
+        let lookup = resolver
+            .lookup(name, record_type)
+            .map_err(|e| e.to_string())?;
+        let elapsed = start.elapsed();
+
+        Ok(Answer {
+            records: lookup
+                .iter()
+                .map(|rdata| Self::normalize(&rdata.to_string()))
+                .collect(),
+            elapsed,
+        })
+    }
+
+    pub fn normalize(record: &str) -> String {
+        record.trim_end_matches('.').to_lowercase()
+    }
+}
@@ -0,0 +1,238 @@
+//  SPDX-FileCopyrightText: Copyright 2023 James M. Putnam (putnamjm.design@gmail.com)
+//  SPDX-License-Identifier: MIT
+
+// hysteresis and flap detection
+//
+// a host's shown state only changes once the polls agree on the
+// new one for long enough: down_after polls in a row to go down,
+// up_after to come back. the hysteresis section of settings
+//
+//     "hysteresis": { "down_after": 2, "up_after": 1, "flap_window": 20, "flap_high": 50, "flap_low": 25 }
+//
+// the flapping score is the percentage of the last flap_window
+// polls that changed state. a host starts flapping when its score
+// reaches flap_high and stops when it falls below flap_low, and
+// no alerts are run for a flapping host
+use {
+    super::host::{Host, State},
+    crate::{settings::Hysteresis, Environment},
+    std::{
+        collections::{HashMap, VecDeque},
+        sync::RwLock,
+    },
+};
+
+#[derive(Debug, Clone)]
+struct Track {
+    state: State,
+    pending: State,
+    streak: usize,
+    polled: VecDeque<State>,
+    flapping: bool,
+}
+
+impl Track {
+    fn new(state: State) -> Self {
+        Track {
+            state,
+            pending: state,
+            streak: 0,
+            polled: VecDeque::new(),
+            flapping: false,
+        }
+    }
+
+    fn score(&self) -> f64 {
+        match self.polled.len() {
+            0 | 1 => 0.0,
+            len => {
+                let changes = self
+                    .polled
+                    .iter()
+                    .zip(self.polled.iter().skip(1))
+                    .filter(|(from, to)| from != to)
+                    .count();
+
+                changes as f64 * 100.0 / (len - 1) as f64
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Flap {
+    settings: Hysteresis,
+    // by host name
+    tracks: RwLock<HashMap<String, Track>>,
+}
+
+impl Flap {
+    pub fn new(env: &Environment) -> Self {
+        Flap {
+            settings: match &env.settings {
+                Some(settings) => settings.hysteresis.clone(),
+                None => Hysteresis::default(),
+            },
+            tracks: RwLock::new(HashMap::new()),
+        }
+    }
+
+    // the states to show for a poll's states. a host seen for the
+    // first time, or whose state isn't known, takes its polled state
+    pub fn apply(&self, hosts: &[Host], polled: &[State]) -> Vec<State> {
+        let mut tracks = self.tracks.write().unwrap();
+
+        tracks.retain(|name, _| hosts.iter().any(|host| &host.host == name));
+
+        hosts
+            .iter()
+            .zip(polled)
+            .map(|(host, polled)| {
+                let track = tracks
+                    .entry(host.host.clone())
+                    .or_insert_with(|| Track::new(*polled));
+
+                track.polled.push_back(*polled);
+                while track.polled.len() > self.settings.flap_window.max(2) {
+                    track.polled.pop_front();
+                }
+
+                if *polled == track.state {
+                    track.streak = 0
+                } else {
+                    if *polled == track.pending {
+                        track.streak += 1
+                    } else {
+                        track.pending = *polled;
+                        track.streak = 1
+                    }
+
+                    let needed = match polled {
+                        State::Down => self.settings.down_after,
+                        State::Unknown => 1,
+                        _ => self.settings.up_after,
+                    };
+
+                    if track.state == State::Unknown || track.streak >= needed {
+                        track.state = *polled;
+                        track.streak = 0
+                    }
+                }
+
+                let score = track.score();
+
+                track.flapping = match track.flapping {
+                    true => score >= self.settings.flap_low,
+                    false => score >= self.settings.flap_high,
+                };
+
+                track.state
+            })
+            .collect()
+    }
+
+    pub fn flapping(&self, hosts: &[Host]) -> Vec<bool> {
+        let tracks = self.tracks.read().unwrap();
+
+        hosts
+            .iter()
+            .map(|host| {
+                tracks
+                    .get(&host.host)
+                    .map(|track| track.flapping)
+                    .unwrap_or(false)
+            })
+            .collect()
+    }
+
+    pub fn score(&self, host: &Host) -> Option<f64> {
+        self.tracks
+            .read()
+            .unwrap()
+            .get(&host.host)
+            .map(|track| track.score())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flap(down_after: usize, flap_window: usize) -> Flap {
+        Flap {
+            settings: Hysteresis {
+                down_after,
+                up_after: 1,
+                flap_window,
+                flap_high: 50.0,
+                flap_low: 25.0,
+            },
+            tracks: RwLock::new(HashMap::new()),
+        }
+    }
+
+    fn hosts() -> Vec<Host> {
+        vec![Host {
+            host: "nas.lan".to_string(),
+            ..Default::default()
+        }]
+    }
+
+    #[test]
+    fn goes_down_after_down_after_polls() {
+        let flap = flap(2, 20);
+        let hosts = hosts();
+        let shown: Vec<State> = [State::Up, State::Down, State::Down, State::Up]
+            .into_iter()
+            .map(|polled| flap.apply(&hosts, &[polled])[0])
+            .collect();
+
+        assert_eq!(shown, [State::Up, State::Up, State::Down, State::Up]);
+    }
+
+    #[test]
+    fn a_lone_poll_resets_the_streak() {
+        let flap = flap(2, 20);
+        let hosts = hosts();
+        let shown: Vec<State> = [State::Up, State::Down, State::Up, State::Down]
+            .into_iter()
+            .map(|polled| flap.apply(&hosts, &[polled])[0])
+            .collect();
+
+        assert_eq!(shown, [State::Up; 4]);
+    }
+
+    #[test]
+    fn flapping_starts_high_and_stops_low() {
+        let flap = flap(1, 5);
+        let hosts = hosts();
+        let flapping: Vec<bool> = [
+            State::Up,
+            State::Up,
+            State::Down,
+            State::Down,
+            State::Down,
+            State::Down,
+            State::Down,
+        ]
+        .into_iter()
+        .map(|polled| {
+            flap.apply(&hosts, &[polled]);
+            flap.flapping(&hosts)[0]
+        })
+        .collect();
+
+        // scores 0, 0, 50, 33, 25, 25, 0
+        assert_eq!(flapping, [false, false, true, true, true, true, false]);
+    }
+
+    #[test]
+    fn forgets_removed_hosts() {
+        let flap = flap(2, 20);
+        let hosts = hosts();
+
+        flap.apply(&hosts, &[State::Up]);
+        flap.apply(&[], &[]);
+        assert_eq!(flap.score(&hosts[0]), None);
+    }
+}
@@ -0,0 +1,170 @@
+//  SPDX-FileCopyrightText: Copyright 2023 James M. Putnam (putnamjm.design@gmail.com)
+//  SPDX-License-Identifier: MIT
+
+// hierarchical host groups
+//
+// a host group is a path, "dc1/rack3/db", and each prefix of
+// the path is a node in the tree. nodes are kept in preorder,
+// siblings in the order they first appear in the hosts file.
+use {
+    super::host::{Host, State},
+    std::sync::RwLock,
+};
+
+#[derive(Debug, Default)]
+pub struct Group {
+    pub path: String,
+    pub name: String,
+    pub depth: usize,
+    pub children: usize,
+    pub hosts: Vec<usize>,
+}
+
+impl Group {
+    pub const SEPARATOR: char = '/';
+
+    // does the group path include the host group, either
+    // directly or as one of its subgroups
+    pub fn contains(path: &str, group: &str) -> bool {
+        path.is_empty()
+            || group == path
+            || (group.starts_with(path) && group[path.len()..].starts_with(Self::SEPARATOR))
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct GroupTree {
+    groups: Vec<Group>,
+    expanded: RwLock<Vec<String>>,
+}
+
+impl GroupTree {
+    pub fn new(hosts: &[Host]) -> Self {
+        let mut groups: Vec<Group> = Vec::new();
+
+        for host in hosts {
+            let mut path = String::new();
+            let mut parent: Option<usize> = None;
+
+            for (depth, name) in host
+                .group
+                .split(Group::SEPARATOR)
+                .filter(|name| !name.is_empty())
+                .enumerate()
+            {
+                if !path.is_empty() {
+                    path.push(Group::SEPARATOR)
+                }
+                path.push_str(name);
+
+                parent = match groups.iter().position(|group| group.path == path) {
+                    Some(id) => Some(id),
+                    None => {
+                        let id = match parent {
+                            Some(parent) => {
+                                groups[parent].children += 1;
+
+                                parent
+                                    + 1
+                                    + groups[parent + 1..]
+                                        .iter()
+                                        .take_while(|group| group.depth > depth - 1)
+                                        .count()
+                            }
+                            None => groups.len(),
+                        };
+
+                        groups.insert(
+                            id,
+                            Group {
+                                path: path.clone(),
+                                name: name.to_string(),
+                                depth,
+                                children: 0,
+                                hosts: Vec::new(),
+                            },
+                        );
+
+                        Some(id)
+                    }
+                }
+            }
+        }
+
+        for group in groups.iter_mut() {
+            group.hosts = hosts
+                .iter()
+                .enumerate()
+                .filter(|(_, host)| Group::contains(&group.path, &host.group))
+                .map(|(id, _)| id)
+                .collect();
+        }
+
+        GroupTree {
+            groups,
+            expanded: RwLock::new(Vec::new()),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.groups.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+
+    pub fn group(&self, id: usize) -> &Group {
+        &self.groups[id]
+    }
+
+    pub fn is_expanded(&self, id: usize) -> bool {
+        let expanded = self.expanded.read().unwrap();
+
+        expanded.contains(&self.groups[id].path)
+    }
+
+    pub fn toggle(&self, id: usize) {
+        let mut expanded = self.expanded.write().unwrap();
+        let path = &self.groups[id].path;
+
+        match expanded.iter().position(|group| group == path) {
+            Some(index) => {
+                expanded.remove(index);
+            }
+            None => expanded.push(path.clone()),
+        }
+    }
+
+    // the nodes not hidden under a collapsed ancestor, in preorder
+    pub fn visible(&self) -> Vec<usize> {
+        let mut visible = Vec::new();
+        let mut collapsed: Option<usize> = None;
+
+        for (id, group) in self.groups.iter().enumerate() {
+            match collapsed {
+                Some(depth) if group.depth > depth => continue,
+                _ => collapsed = None,
+            }
+
+            visible.push(id);
+            if !self.is_expanded(id) {
+                collapsed = Some(group.depth)
+            }
+        }
+
+        visible
+    }
+
+    // (up, down) over every host in the group and its subgroups.
+    // degraded hosts answer, so count as up
+    pub fn counts(&self, id: usize, states: &[State]) -> (usize, usize) {
+        let hosts = &self.groups[id].hosts;
+        let count = |state| hosts.iter().filter(|host| states[**host] == state).count();
+
+        (
+            count(State::Up) + count(State::Degraded),
+            count(State::Down),
+        )
+    }
+}
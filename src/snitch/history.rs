@@ -0,0 +1,68 @@
+//  SPDX-FileCopyrightText: Copyright 2023 James M. Putnam (putnamjm.design@gmail.com)
+//  SPDX-License-Identifier: MIT
+
+// poll history
+//
+// every change in a host's state is appended to history.log in
+// the state directory, one line per change
+//
+//     2023-06-01 12:00:00 router.lan up down
+use {
+    super::host::{Host, State},
+    crate::Environment,
+    std::{fs::OpenOptions, io::Write, path::PathBuf},
+};
+
+#[derive(Debug, Default)]
+pub struct History {
+    path: PathBuf,
+}
+
+impl History {
+    const FILE: &str = "history.log";
+
+    pub fn new(env: &Environment) -> Self {
+        History {
+            path: env.state_path.join(Self::FILE),
+        }
+    }
+
+    // the hosts whose state changed between two polls of the same
+    // hosts. the first poll has nothing to compare with
+    pub fn record(&self, hosts: &[Host], last: &[State], states: &[State]) -> std::io::Result<()> {
+        if last.len() != states.len() || hosts.len() != states.len() {
+            return Ok(());
+        }
+
+        let now =
+            time::OffsetDateTime::now_local().unwrap_or_else(|_| time::OffsetDateTime::now_utc());
+        let mut lines = String::new();
+
+        for (id, host) in hosts.iter().enumerate() {
+            if last[id] != states[id] {
+                lines.push_str(&format!(
+                    "{:04}-{:02}-{:02} {:02}:{:02}:{:02} {} {} {}\n",
+                    now.year(),
+                    now.month() as u8,
+                    now.day(),
+                    now.hour(),
+                    now.minute(),
+                    now.second(),
+                    host.host,
+                    last[id].as_str(),
+                    states[id].as_str()
+                ))
+            }
+        }
+
+        if lines.is_empty() {
+            return Ok(());
+        }
+
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?
+            .write_all(lines.as_bytes())
+    }
+}
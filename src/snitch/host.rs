@@ -3,8 +3,17 @@
 #![allow(unused_imports)]
 
 use {
-    crate::Environment,
-    dns_lookup::lookup_host,
+    super::{
+        burst::Burst,
+        depend::Depend,
+        dns::Dns,
+        flap::Flap,
+        group::Group,
+        import::Import,
+        maintenance::Schedule,
+        probe::{Check, Probe},
+    },
+    crate::{format::Format, interpolate::Templates, settings::Ping, Environment},
     fastping_rs::{
         NewPingerResult,
         PingResult::{self, Idle, Receive},
@@ -13,133 +22,846 @@ use {
     serde::{Deserialize, Serialize},
     serde_json::{Result as SerdeResult, Value},
     std::{
+        collections::{HashMap, HashSet},
         error::Error,
         fs::File,
         io::BufReader,
-        path::Path,
-        sync::{mpsc::Receiver, RwLock},
+        net::IpAddr,
+        path::{Path, PathBuf},
+        sync::{mpsc::Receiver, Mutex, RwLock},
+        time::Duration,
     },
 };
 
+// shared between the front ends and the thread they poll in. a
+// poll keeps the pinger to itself, and swaps in its bursts and
+// checks once it's done, so they can be read meanwhile
 pub struct Poll {
-    pinger: RwLock<Pinger>,
-    results: Receiver<PingResult>,
+    pub dns: Dns,
+    pub flap: Flap,
+    pub schedule: Schedule,
+    pinger: Mutex<Pinger>,
+    results: Mutex<Receiver<PingResult>>,
+    ping: Ping,
+    // the last burst sent to each address
+    bursts: RwLock<HashMap<String, Burst>>,
+    // the last probe checks of each host, by host name
+    checks: RwLock<HashMap<String, Vec<Check>>>,
 }
 
 impl Poll {
-    pub fn new(_env: &Environment) -> Self {
-        let (pinger, results) = match Pinger::new(Some(1000_u64), None) {
-            Ok((pinger, results)) => (pinger, results),
-            Err(e) => panic!("Error creating pinger: {}", e),
-        };
+    // fails without the pinger's raw socket, which needs root or
+    // cap_net_raw
+    pub fn new(env: &Environment) -> Result<Self, String> {
+        let (pinger, results) = Pinger::new(Some(1000_u64), None)
+            .map_err(|e| format!("can't open the ping socket: {}", e))?;
 
-        Self {
-            pinger: RwLock::new(pinger),
-            results,
-        }
+        Ok(Self {
+            dns: Dns::new(env),
+            flap: Flap::new(env),
+            schedule: Schedule::new(env),
+            pinger: Mutex::new(pinger),
+            results: Mutex::new(results),
+            ping: match &env.settings {
+                Some(settings) => settings.ping.clone(),
+                None => Ping::default(),
+            },
+            bursts: RwLock::new(HashMap::new()),
+            checks: RwLock::new(HashMap::new()),
+        })
     }
 
-    pub fn poll(&self, host: &Host) -> bool {
-        let pinger = self.pinger.write().unwrap();
+    pub fn poll_all(&self, hosts: &[Host]) -> Vec<State> {
+        let mut states = vec![State::Unknown; hosts.len()];
 
-        match lookup_host(&host.host) {
-            Ok(ips) => {
-                let ip_addr = ips[0];
-                pinger.add_ipaddr(&ip_addr.to_string());
-                pinger.run_pinger();
+        self.schedule.refresh(hosts);
 
-                let state = match self.results.recv() {
-                    Ok(result) => match result {
-                        Idle { addr: _ } => false,
-                        Receive { addr: _, rtt: _ } => true,
-                    },
-                    Err(_) => false, // panic!("Worker threads disconnected before the solution was found!"),
-                };
+        // hosts that have never resolved stay unknown and aren't pinged
+        let ipaddrs: Vec<Option<String>> = hosts
+            .iter()
+            .map(|host| self.dns.resolve(&host.host).map(|addr| addr.to_string()))
+            .collect();
+        self.dns.save().ok();
+
+        let mut bursts = HashMap::new();
 
-                pinger.remove_ipaddr(&ip_addr.to_string());
+        // hosts stay unknown if the pinger has stopped
+        for (addr, burst) in self
+            .ping(
+                ipaddrs.iter().flatten().cloned().collect(),
+                self.ping.count.max(1),
+            )
+            .unwrap_or_default()
+        {
+            for (id, ipaddr) in ipaddrs.iter().enumerate() {
+                if ipaddr.as_ref() == Some(&addr) {
+                    let thresholds = &hosts[id].thresholds;
+                    let state = burst.state(
+                        thresholds.loss_pct.unwrap_or(self.ping.degraded_loss),
+                        self.ping.down_loss,
+                    );
 
-                state
+                    states[id] = match (burst.avg(), thresholds.rtt_ms) {
+                        (Some(rtt), Some(limit))
+                            if state == State::Up && Thresholds::millis(rtt) > limit =>
+                        {
+                            State::Degraded
+                        }
+                        _ => state,
+                    }
+                }
             }
-            Err(_) => false, // panic!("hostname: {} DNS lookup failure", host.host),
+
+            bursts.insert(addr, burst);
         }
+
+        // probe each host in its own thread, whether or not it answers
+        // pings, so a host that filters them can still be checked
+        let mut checks = HashMap::new();
+
+        std::thread::scope(|scope| {
+            let probes: Vec<_> = hosts
+                .iter()
+                .enumerate()
+                .filter(|(_, host)| !host.probes.is_empty())
+                .map(|(id, host)| {
+                    (
+                        id,
+                        scope.spawn(move || {
+                            host.probes
+                                .iter()
+                                .map(|probe| probe.run(&host.host))
+                                .collect::<Vec<Check>>()
+                        }),
+                    )
+                })
+                .collect();
+
+            // a host that answers takes the worst of its ping and its
+            // probes, one that doesn't takes its probes' state
+            for (id, probe) in probes {
+                if let Ok(host_checks) = probe.join() {
+                    let probe_state = Host::probe_state(&hosts[id], &host_checks);
+
+                    states[id] = match states[id] {
+                        State::Up | State::Degraded => states[id].worst(probe_state),
+                        _ => probe_state,
+                    };
+                    checks.insert(hosts[id].host.clone(), host_checks);
+                }
+            }
+        });
+
+        *self.bursts.write().unwrap() = bursts;
+        *self.checks.write().unwrap() = checks;
+
+        Depend::apply(hosts, &self.flap.apply(hosts, &states))
     }
 
-    pub fn poll_all(&self, hosts: &[Host]) -> Vec<bool> {
-        let mut states = vec![false; hosts.len()];
-        let pinger = self.pinger.write().unwrap();
+    // hosts whose alerts aren't run: flapping, behind a down parent,
+    // in a maintenance window, or silenced
+    pub fn quiet(&self, hosts: &[Host], states: &[State]) -> Vec<bool> {
+        self.flap
+            .flapping(hosts)
+            .into_iter()
+            .zip(hosts.iter().zip(states))
+            .map(|(flapping, (host, state))| {
+                flapping || *state == State::Unreachable || self.schedule.mark(host).is_some()
+            })
+            .collect()
+    }
 
-        let ipaddrs: Vec<String> = hosts
+    // what the host grid notes after each host's label
+    pub fn notes(&self, hosts: &[Host], states: &[State]) -> Vec<Vec<&'static str>> {
+        hosts
             .iter()
-            .map(|host| match lookup_host(&host.host) {
-                Ok(ips) => {
-                    let ip_addr = ips[0].to_string();
-                    pinger.add_ipaddr(&ip_addr);
-                    ip_addr
+            .zip(states)
+            .map(|(host, state)| {
+                let mut notes = Vec::new();
+
+                if self.dns.failing(&host.host) {
+                    notes.push("dns")
+                }
+                if *state == State::Unreachable {
+                    notes.push("parent down")
                 }
-                Err(_) => "".to_string(),
+                notes.extend(self.schedule.mark(host));
+
+                notes
             })
-            .collect();
+            .collect()
+    }
+
+    // as of the last poll
+    pub fn burst(&self, host: &Host) -> Option<Burst> {
+        let addr = self.dns.resolve(&host.host)?.to_string();
+
+        self.bursts.read().unwrap().get(&addr).cloned()
+    }
+
+    // as of the last poll, one for each of the host's probes
+    pub fn checks(&self, host: &Host) -> Vec<Check> {
+        self.checks
+            .read()
+            .unwrap()
+            .get(&host.host)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    // which hosts' names are failing to resolve, whatever their state
+    pub fn dns_failing(&self, hosts: &[Host]) -> Vec<bool> {
+        hosts
+            .iter()
+            .map(|host| self.dns.failing(&host.host))
+            .collect()
+    }
+
+    // the addresses that answer, in batch
+    pub fn sweep(&self, addrs: &[IpAddr]) -> Result<Vec<IpAddr>, String> {
+        Ok(self
+            .ping(addrs.iter().map(|addr| addr.to_string()).collect(), 1)?
+            .into_iter()
+            .filter(|(_, burst)| burst.received() > 0)
+            .filter_map(|(addr, _)| addr.parse().ok())
+            .collect())
+    }
+
+    // ping each distinct address count times, a round at a time, and
+    // return their bursts in the order they complete
+    fn ping(
+        &self,
+        mut addrs: HashSet<String>,
+        count: usize,
+    ) -> Result<Vec<(String, Burst)>, String> {
+        let pinger = self.pinger.lock().unwrap();
+        let results = self.results.lock().unwrap();
+        let mut bursts: HashMap<String, Burst> = HashMap::new();
+        let mut done = Vec::new();
+
+        if addrs.is_empty() {
+            return Ok(done);
+        }
+
+        for addr in &addrs {
+            pinger.add_ipaddr(addr);
+        }
 
         pinger.run_pinger();
 
-        for _ in 0..hosts.len() {
-            let (addr, state) = match self.results.recv() {
+        while !addrs.is_empty() {
+            let (addr, rtt) = match results.recv() {
                 Ok(result) => match result {
-                    Idle { addr } => (addr, false),
-                    Receive { addr, rtt: _ } => (addr, true),
+                    Idle { addr } => (addr.to_string(), None),
+                    Receive { addr, rtt } => (addr.to_string(), Some(rtt)),
                 },
-                Err(_) => panic!("Worker threads disconnected before the solution was found!"),
+                Err(_) => {
+                    for addr in &addrs {
+                        pinger.remove_ipaddr(addr)
+                    }
+                    return Err("the pinger stopped before every address answered".to_string());
+                }
             };
 
-            pinger.remove_ipaddr(&addr.to_string());
-            states[ipaddrs
-                .iter()
-                .position(|ipaddr| &addr.to_string() == ipaddr)
-                .unwrap()] = state;
+            // a late reply for an address already done
+            if !addrs.contains(&addr) {
+                continue;
+            }
+
+            let burst = bursts.entry(addr.clone()).or_default();
+
+            burst.sent += 1;
+            burst.rtts.extend(rtt);
+
+            if burst.sent >= count {
+                pinger.remove_ipaddr(&addr);
+                addrs.remove(&addr);
+                done.push((addr.clone(), bursts.remove(&addr).unwrap_or_default()))
+            }
         }
 
-        states
+        Ok(done)
     }
 }
 
-#[derive(Debug, Default, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum State {
+    Up,
+    Degraded,
+    Down,
+    // down behind a down parent
+    Unreachable,
+    #[default]
+    Unknown,
+}
+
+impl State {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            State::Up => "up",
+            State::Degraded => "degraded",
+            State::Down => "down",
+            State::Unreachable => "unreachable",
+            State::Unknown => "unknown",
+        }
+    }
+
+    pub fn count(states: &[State], state: State) -> usize {
+        states.iter().filter(|st| **st == state).count()
+    }
+
+    // for taking the worst of several states: down, or unreachable,
+    // over degraded over unknown over up
+    pub fn severity(&self) -> u8 {
+        match self {
+            State::Up => 0,
+            State::Unknown => 1,
+            State::Degraded => 2,
+            State::Down | State::Unreachable => 3,
+        }
+    }
+
+    pub fn worst(self, other: State) -> State {
+        match other.severity() > self.severity() {
+            true => other,
+            false => self,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct Host {
     pub group: String,
     pub host: String,
     pub label: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub comment: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub probes: Vec<Probe>,
+    #[serde(default, skip_serializing_if = "Thresholds::is_empty")]
+    pub thresholds: Thresholds,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<String>,
+    // the file the host was read from
+    #[serde(skip)]
+    pub source: PathBuf,
+    #[serde(skip)]
+    pub templates: Templates,
+}
+
+// limits past which a host that answers is degraded, in hosts.json
+//
+//     "thresholds": { "rtt_ms": 200, "probe_ms": 1000, "loss_pct": 10 }
+//
+// rtt_ms is the average ping round trip, probe_ms the time any
+// probe takes, and loss_pct overrides the ping degraded_loss setting
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Thresholds {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rtt_ms: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub probe_ms: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loss_pct: Option<f64>,
+}
+
+impl Thresholds {
+    pub fn is_empty(&self) -> bool {
+        self.rtt_ms.is_none() && self.probe_ms.is_none() && self.loss_pct.is_none()
+    }
+
+    pub fn millis(duration: Duration) -> f64 {
+        duration.as_secs_f64() * 1000.0
+    }
 }
 
 impl Host {
-    pub fn info(poll: &Poll, host: &Host) -> String {
-        match lookup_host(&host.host) {
-            Ok(ips) => {
-                let ip_addr = ips[0];
-
-                format!(
-                    "{:?} {}",
-                    ip_addr,
-                    if poll.poll(host) { "up" } else { "down" },
-                )
+    pub const FRAGMENTS: &str = "hosts.d";
+
+    // in the group filter and matching the search, by label or host name
+    pub fn matches(&self, filter: &str, search: &str) -> bool {
+        let search = search.to_lowercase();
+
+        Group::contains(filter, &self.group)
+            && (self.label.to_lowercase().contains(&search)
+                || self.host.to_lowercase().contains(&search))
+    }
+
+    // expand ${VAR} and file: in the string fields
+    pub fn interpolate(&mut self) -> Result<(), String> {
+        let templates = &mut self.templates;
+
+        for (key, field) in [
+            ("group", &mut self.group),
+            ("host", &mut self.host),
+            ("label", &mut self.label),
+            ("comment", &mut self.comment),
+        ] {
+            templates.field(key, field)?
+        }
+
+        for (n, parent) in self.depends_on.iter_mut().enumerate() {
+            templates.field(&format!("depends_on.{}", n), parent)?
+        }
+
+        for (n, probe) in self.probes.iter_mut().enumerate() {
+            probe.interpolate(&format!("probes.{}", n), templates)?
+        }
+
+        Ok(())
+    }
+
+    // the host as it was written, before interpolation
+    pub fn restored(&self) -> Host {
+        let restore = |key: &str, field: &str| self.templates.restore(key, field);
+
+        Host {
+            group: restore("group", &self.group),
+            host: restore("host", &self.host),
+            label: restore("label", &self.label),
+            comment: restore("comment", &self.comment),
+            probes: self
+                .probes
+                .iter()
+                .enumerate()
+                .map(|(n, probe)| probe.restored(&format!("probes.{}", n), &self.templates))
+                .collect(),
+            depends_on: self
+                .depends_on
+                .iter()
+                .enumerate()
+                .map(|(n, parent)| restore(&format!("depends_on.{}", n), parent))
+                .collect(),
+            ..self.clone()
+        }
+    }
+
+    // a host that answers pings takes the worst of its probes'
+    // states: any probe down makes it down, then degraded, then
+    // unknown. a probe that's up but slower than the probe_ms
+    // threshold counts as degraded
+    pub fn probe_state(host: &Host, checks: &[Check]) -> State {
+        checks
+            .iter()
+            .map(
+                |check| match (check.state, check.elapsed, host.thresholds.probe_ms) {
+                    (State::Up, Some(elapsed), Some(limit))
+                        if Thresholds::millis(elapsed) > limit =>
+                    {
+                        State::Degraded
+                    }
+                    (state, ..) => state,
+                },
+            )
+            .fold(State::Up, State::worst)
+    }
+
+    // lines for the front ends' host detail panels, secrets redacted.
+    // state is the host's as of the last poll
+    pub fn details(poll: &Poll, host: &Host, state: State) -> Vec<String> {
+        let mut details = vec![
+            format!("host: {}", host.host),
+            format!("group: {}", host.group),
+            format!("label: {}", host.label),
+            format!("info: {}", Host::info(poll, host, state)),
+        ];
+
+        if !host.comment.is_empty() {
+            details.push(format!("comment: {}", host.comment))
+        }
+
+        if poll.schedule.in_maintenance(host) {
+            details.push("in maintenance".to_string())
+        }
+
+        if let Some(until) = poll.schedule.silenced_until(host) {
+            details.push(format!("silenced until {}", until.format("%Y-%m-%d %H:%M")))
+        }
+
+        if !host.depends_on.is_empty() {
+            details.push(format!("depends on: {}", host.depends_on.join(" ")))
+        }
+
+        if let Some(burst) = poll.burst(host) {
+            details.push(burst.line())
+        }
+
+        if let Some(score) = poll.flap.score(host) {
+            details.push(format!("flapping score: {:.0}%", score))
+        }
+
+        // the last poll's checks, not run again here
+        let checks = poll.checks(host);
+
+        for (n, probe) in host.probes.iter().enumerate() {
+            match checks.get(n) {
+                Some(check) => {
+                    details.push(format!("{}: {}", probe, check));
+                    details.extend(check.lines.iter().cloned())
+                }
+                None => details.push(format!("{}: not checked", probe)),
             }
-            Err(_) => format!("hostname: {} DNS lookup failure", host.host),
         }
+
+        details
+            .iter()
+            .map(|line| host.templates.redact(line))
+            .collect()
     }
 
-    pub fn load(env: &Environment) -> Option<Vec<Host>> {
-        let dot_path = &std::path::Path::join(&env.config_path, "hosts.json");
-        let path = match &env.hosts_path {
-            Some(path) => path,
-            None => dot_path,
+    pub fn info(poll: &Poll, host: &Host, state: State) -> String {
+        match poll.dns.resolve(&host.host) {
+            Some(ip_addr) if poll.dns.failing(&host.host) => format!(
+                "{:?} {} (dns failing, cached address)",
+                ip_addr,
+                state.as_str()
+            ),
+            Some(ip_addr) => format!("{:?} {}", ip_addr, state.as_str()),
+            None => format!("hostname: {} DNS lookup failure", host.host),
+        }
+    }
+
+    // the primary hosts file, where new hosts are added: the first
+    // file given, else the hosts file in the config directory
+    pub fn path(env: &Environment) -> PathBuf {
+        match env.hosts_paths.iter().find(|path| !path.is_dir()) {
+            Some(path) => path.clone(),
+            None => Self::hosts_file(&env.config_path)
+                .unwrap_or_else(|| Path::join(&env.config_path, "hosts.json")),
+        }
+    }
+
+    // the first of hosts.json, hosts.toml, hosts.yaml, hosts.yml
+    // in a directory that exists
+    fn hosts_file(dir: &Path) -> Option<PathBuf> {
+        Format::EXTENSIONS
+            .iter()
+            .map(|ext| Path::join(dir, format!("hosts.{}", ext)))
+            .find(|path| path.exists())
+    }
+
+    // the host files to merge, in order: those given, or the primary
+    // hosts file followed by the fragments in the config directory's
+    // hosts.d. without either, the shared host lists in /etc/rsnitch-rs.
+    // includes aren't followed here
+    pub fn paths(env: &Environment) -> Vec<PathBuf> {
+        if !env.hosts_paths.is_empty() {
+            return env
+                .hosts_paths
+                .iter()
+                .flat_map(|path| Self::expand(path))
+                .collect();
+        }
+
+        let user: Vec<PathBuf> = [
+            Self::path(env),
+            Path::join(&env.config_path, Self::FRAGMENTS),
+        ]
+        .iter()
+        .flat_map(|path| Self::expand(path))
+        .collect();
+
+        if !user.is_empty() {
+            return user;
+        }
+
+        let system = Path::new(Environment::SYSTEM_PATH);
+
+        Self::hosts_file(system)
+            .into_iter()
+            .chain(Self::expand(&Path::join(system, Self::FRAGMENTS)))
+            .collect()
+    }
+
+    // every host file read, includes and all, in merge order
+    pub fn files(env: &Environment) -> Vec<PathBuf> {
+        Self::read_all(env).0
+    }
+
+    // a directory stands for the host files in it, by name
+    fn expand(path: &Path) -> Vec<PathBuf> {
+        if path.is_dir() {
+            let mut fragments: Vec<PathBuf> = match std::fs::read_dir(path) {
+                Ok(entries) => entries
+                    .flatten()
+                    .map(|entry| entry.path())
+                    .filter(|path| Format::from_path(path).is_some())
+                    .collect(),
+                Err(_) => Vec::new(),
+            };
+
+            fragments.sort();
+            fragments
+        } else if path.exists() {
+            vec![path.to_path_buf()]
+        } else {
+            Vec::new()
+        }
+    }
+
+    pub fn parse(format: Format, text: &str) -> Result<Vec<Host>, String> {
+        HostsFile::parse(format, text).map(|file| file.hosts)
+    }
+
+    pub fn serialize(format: Format, hosts: &[Host]) -> Result<String, String> {
+        HostsFile::from(hosts.to_vec()).serialize(format)
+    }
+
+    // write hosts back to the files they belong to, by their source,
+    // leaving every other file as it is
+    pub fn write_back(added: &[Host]) -> Result<(), String> {
+        let mut sources: Vec<&Path> = Vec::new();
+
+        for host in added {
+            if !sources.contains(&host.source.as_path()) {
+                sources.push(&host.source)
+            }
+        }
+
+        for source in sources {
+            let hosts: Vec<Host> = added
+                .iter()
+                .filter(|host| host.source == source)
+                .cloned()
+                .collect();
+
+            Self::append(source, &hosts).map_err(|e| format!("{}: {}", source.display(), e))?
+        }
+
+        Ok(())
+    }
+
+    // add hosts to a hosts file, in the format of its extension,
+    // json if it has none. what's already in the file is kept as
+    // written, includes, ${VAR} and file: references and all. toml
+    // [[hosts]] tables, and the items of a yaml list, go on the end
+    // of the text, keeping comments
+    fn append(path: &Path, added: &[Host]) -> std::io::Result<()> {
+        let invalid = |e: String| std::io::Error::new(std::io::ErrorKind::InvalidData, e);
+
+        if added.is_empty() {
+            return Ok(());
+        }
+
+        if Import::from_path(path).is_some() {
+            return Err(invalid("not a hosts file".to_string()));
+        }
+
+        let format = Format::from_path(path).unwrap_or(Format::Json);
+        let added: Vec<Host> = added.iter().map(|host| host.restored()).collect();
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => {
+                let mut file = HostsFile::raw(format, &text).map_err(invalid)?;
+                let list = matches!(format.parse::<Value>(&text), Ok(Value::Array(_)));
+
+                match format {
+                    Format::Toml => Self::append_text(text, HostsFile::from(added).text(format)),
+                    Format::Yaml if list => {
+                        Self::append_text(text, HostsFile::from(added).text(format))
+                    }
+                    _ => {
+                        file.hosts.extend(added);
+                        file.text(format)
+                    }
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                HostsFile::from(added).text(format)
+            }
+            Err(e) => return Err(e),
+        };
+
+        let mut text = text.map_err(invalid)?;
+        if !text.ends_with('\n') {
+            text.push('\n')
+        }
+
+        std::fs::write(path, text)
+    }
+
+    fn append_text(mut text: String, added: Result<String, String>) -> Result<String, String> {
+        if !text.is_empty() && !text.ends_with('\n') {
+            text.push('\n')
+        }
+
+        Ok(text + &added?)
+    }
+
+    // the host files merged in order, a host in more than one file
+    // taken from the first. without any, use an sntoprc if there is one
+    // hosts that depend on each other in a cycle are rejected
+    pub fn load(env: &Environment) -> Result<Vec<Host>, String> {
+        let (files, hosts) = Self::read_all(env);
+
+        if let Some(cycle) = Depend::host_cycle(&hosts) {
+            return Err(format!("dependency cycle: {}", cycle.join(" -> ")));
+        }
+
+        if files.is_empty() {
+            return match Import::sntoprc(&env.home_path) {
+                Some(sntoprc) => Import::Sntop
+                    .read(&sntoprc, None)
+                    .map(|hosts| Self::from_source(hosts, &sntoprc))
+                    .map_err(|e| format!("{}: {}", sntoprc.display(), e)),
+                None => Ok(Vec::new()),
+            };
+        }
+
+        Ok(hosts)
+    }
+
+    fn from_source(hosts: Vec<Host>, source: &Path) -> Vec<Host> {
+        hosts
+            .into_iter()
+            .map(|host| Host {
+                source: source.to_path_buf(),
+                ..host
+            })
+            .collect()
+    }
+
+    fn read_all(env: &Environment) -> (Vec<PathBuf>, Vec<Host>) {
+        let mut files = Vec::new();
+        let mut hosts = Vec::new();
+
+        for path in Self::paths(env) {
+            Self::read(&path, &mut files, &mut hosts)
+        }
+
+        (files, hosts)
+    }
+
+    // a hosts file, or a host list one of the importers recognizes,
+    // then the files it includes, relative to it. a file already
+    // read, say by an include cycle, is skipped
+    fn read(path: &Path, files: &mut Vec<PathBuf>, hosts: &mut Vec<Host>) {
+        let canonical = path.canonicalize().ok();
+
+        if files
+            .iter()
+            .any(|file| file.canonicalize().ok() == canonical)
+        {
+            return;
+        }
+        files.push(path.to_path_buf());
+
+        if let Some(import) = Import::from_path(path) {
+            match import.read(path, None) {
+                Ok(imported) => {
+                    Import::merge(hosts, Self::from_source(imported, path));
+                }
+                Err(e) => eprintln!("{}: {}", path.display(), e),
+            }
+            return;
+        }
+
+        let file = match std::fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| {
+                HostsFile::parse(Format::from_path(path).unwrap_or(Format::Json), &text)
+            }) {
+            Ok(file) => file,
+            Err(e) => {
+                eprintln!("{}: {}", path.display(), e);
+                return;
+            }
         };
 
-        match File::open(path) {
-            Ok(file) => {
-                let reader = BufReader::new(file);
+        Import::merge(hosts, Self::from_source(file.hosts, path));
 
-                let hosts_vec: Vec<Host> = serde_json::from_reader(reader).unwrap();
-                Some(hosts_vec)
+        let dir = path.parent().unwrap_or_else(|| Path::new("."));
+        for include in &file.include {
+            let include = Path::join(dir, include);
+
+            if !include.exists() {
+                eprintln!(
+                    "{}: include {} not found",
+                    path.display(),
+                    include.display()
+                );
+            }
+
+            for path in Self::expand(&include) {
+                Self::read(&path, files, hosts)
             }
-            Err(_) => None,
+        }
+    }
+}
+
+// a hosts file is a list of hosts, or a table of them and the
+// host files it includes. toml wants a table at the top, so a
+// toml hosts file is always a list of [[hosts]] tables
+#[derive(Default, Serialize, Deserialize)]
+pub struct HostsFile {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
+    #[serde(default)]
+    pub hosts: Vec<Host>,
+    #[serde(skip)]
+    pub templates: Templates,
+}
+
+impl From<Vec<Host>> for HostsFile {
+    fn from(hosts: Vec<Host>) -> Self {
+        HostsFile {
+            hosts,
+            ..Default::default()
+        }
+    }
+}
+
+impl HostsFile {
+    // interpolated, see Interpolate
+    pub fn parse(format: Format, text: &str) -> Result<HostsFile, String> {
+        let mut file = Self::raw(format, text)?;
+
+        for (n, include) in file.include.iter_mut().enumerate() {
+            file.templates
+                .field(&format!("include.{}", n), include)
+                .map_err(|e| format!("include: {}", e))?
+        }
+
+        for (n, host) in file.hosts.iter_mut().enumerate() {
+            host.interpolate()
+                .map_err(|e| format!("host {}: {}", n + 1, e))?
+        }
+
+        Ok(file)
+    }
+
+    // as written, ${VAR} and file: references left as they are
+    pub fn raw(format: Format, text: &str) -> Result<HostsFile, String> {
+        match format {
+            Format::Toml => format.parse(text),
+            _ => match format.parse::<Value>(text)? {
+                Value::Array(_) => Ok(HostsFile::from(format.parse::<Vec<Host>>(text)?)),
+                _ => format.parse(text),
+            },
+        }
+    }
+
+    // with the interpolated fields as they were written
+    pub fn serialize(&self, format: Format) -> Result<String, String> {
+        HostsFile {
+            include: self
+                .include
+                .iter()
+                .enumerate()
+                .map(|(n, include)| self.templates.restore(&format!("include.{}", n), include))
+                .collect(),
+            hosts: self.hosts.iter().map(|host| host.restored()).collect(),
+            templates: Templates::default(),
+        }
+        .text(format)
+    }
+
+    // a plain list unless there are includes to keep
+    pub fn text(&self, format: Format) -> Result<String, String> {
+        match format {
+            Format::Toml => format.serialize(self),
+            _ if self.include.is_empty() => format.serialize(&self.hosts),
+            _ => format.serialize(self),
         }
     }
 }
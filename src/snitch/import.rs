@@ -0,0 +1,500 @@
+//  SPDX-FileCopyrightText: Copyright 2023 James M. Putnam (putnamjm.design@gmail.com)
+//  SPDX-License-Identifier: MIT
+
+// host importers
+//
+// turn other host lists into Host records: /etc/hosts,
+// ssh_config Host blocks, csv (host,group,label), sntoprc,
+// and nmap -oX scan results
+use {
+    super::{host::Host, probe::Probe},
+    std::{
+        fs, io,
+        net::IpAddr,
+        path::{Path, PathBuf},
+    },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Import {
+    EtcHosts,
+    SshConfig,
+    Csv,
+    Sntop,
+    Nmap,
+}
+
+impl Import {
+    pub const DEFAULT_GROUP: &str = "imported";
+    pub const SNTOP_GROUP: &str = "sntop";
+    pub const SNTOPRC: [&str; 2] = [".sntoprc", "/etc/sntoprc"];
+
+    pub fn from_name(name: &str) -> Option<Import> {
+        match name {
+            "etc-hosts" => Some(Import::EtcHosts),
+            "ssh-config" => Some(Import::SshConfig),
+            "csv" => Some(Import::Csv),
+            "sntop" => Some(Import::Sntop),
+            "nmap" => Some(Import::Nmap),
+            _ => None,
+        }
+    }
+
+    // the user's ~/.sntoprc, else the system /etc/sntoprc
+    pub fn sntoprc(home_path: &Path) -> Option<PathBuf> {
+        Self::SNTOPRC
+            .iter()
+            .map(|path| Path::join(home_path, path))
+            .find(|path| path.exists())
+    }
+
+    // recognize a host list that isn't hosts.json by its name
+    pub fn from_path(path: &Path) -> Option<Import> {
+        let name = path.file_name()?.to_str()?;
+
+        match name {
+            "hosts" => Some(Import::EtcHosts),
+            "config" | "ssh_config" => Some(Import::SshConfig),
+            ".sntoprc" | "sntoprc" => Some(Import::Sntop),
+            _ => match path.extension()?.to_str()? {
+                "csv" => Some(Import::Csv),
+                _ => None,
+            },
+        }
+    }
+
+    pub fn read(&self, path: &Path, group: Option<&str>) -> io::Result<Vec<Host>> {
+        let text = fs::read_to_string(path)?;
+        let mut hosts = Vec::new();
+
+        Self::merge(&mut hosts, self.parse(&text, group)?);
+
+        Ok(hosts)
+    }
+
+    // without a group, sntop hosts go in the sntop group, nmap hosts
+    // in their subnet's group, and the rest in the imported group
+    pub fn parse(&self, text: &str, group: Option<&str>) -> io::Result<Vec<Host>> {
+        let default = match self {
+            Import::Sntop => Self::SNTOP_GROUP,
+            _ => Self::DEFAULT_GROUP,
+        };
+
+        match self {
+            Import::EtcHosts => Ok(Self::etc_hosts(text, group.unwrap_or(default))),
+            Import::SshConfig => Ok(Self::ssh_config(text, group.unwrap_or(default))),
+            Import::Csv => Ok(Self::csv(text, group.unwrap_or(default))),
+            Import::Sntop => Ok(Self::sntop(text, group.unwrap_or(default))),
+            Import::Nmap => Self::nmap(text, group),
+        }
+    }
+
+    // append the imported hosts not already present, by host
+    // name, and return how many were added
+    pub fn merge(hosts: &mut Vec<Host>, imported: Vec<Host>) -> usize {
+        let len = hosts.len();
+
+        for host in imported {
+            if !hosts
+                .iter()
+                .any(|present| present.host.eq_ignore_ascii_case(&host.host))
+            {
+                hosts.push(host)
+            }
+        }
+
+        hosts.len() - len
+    }
+
+    fn host(host: &str, group: &str, label: &str) -> Host {
+        Host {
+            group: group.to_string(),
+            host: host.to_string(),
+            label: label.to_string(),
+            ..Default::default()
+        }
+    }
+
+    // address canonical_name aliases...
+    //
+    // loopback, multicast and the other ipv6 boilerplate entries
+    // aren't worth monitoring
+    fn etc_hosts(text: &str, group: &str) -> Vec<Host> {
+        let mut hosts = Vec::new();
+
+        for line in text.lines() {
+            let line = match line.find('#') {
+                Some(comment) => &line[..comment],
+                None => line,
+            };
+
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() < 2 {
+                continue;
+            }
+
+            let addr = fields[0];
+            if addr.starts_with("127.")
+                || addr == "::1"
+                || addr.starts_with("ff0")
+                || addr.starts_with("fe00:")
+            {
+                continue;
+            }
+
+            let name = fields[1];
+            let label = name.split('.').next().unwrap_or(name);
+
+            hosts.push(Self::host(name, group, label))
+        }
+
+        hosts
+    }
+
+    // Host alias... blocks, using HostName when the block has one.
+    // patterns (*, ?, !) and Match blocks don't name a host
+    fn ssh_config(text: &str, group: &str) -> Vec<Host> {
+        let mut hosts = Vec::new();
+        let mut aliases: Vec<String> = Vec::new();
+        let mut hostname: Option<String> = None;
+
+        let mut block = |aliases: &mut Vec<String>, hostname: &mut Option<String>| {
+            for alias in aliases.drain(..) {
+                let host = match hostname {
+                    Some(hostname) => hostname.clone(),
+                    None => alias.clone(),
+                };
+
+                hosts.push(Self::host(&host, group, &alias))
+            }
+            *hostname = None;
+        };
+
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (keyword, args) = match line.find(|ch: char| ch.is_whitespace() || ch == '=') {
+                Some(split) => (
+                    &line[..split],
+                    line[split..].trim_start_matches(|ch: char| ch.is_whitespace() || ch == '='),
+                ),
+                None => (line, ""),
+            };
+
+            match keyword.to_lowercase().as_str() {
+                "host" => {
+                    block(&mut aliases, &mut hostname);
+                    aliases = args
+                        .split_whitespace()
+                        .filter(|alias| !alias.contains(['*', '?', '!']))
+                        .map(|alias| alias.to_string())
+                        .collect();
+                }
+                "match" => block(&mut aliases, &mut hostname),
+                "hostname" if !aliases.is_empty() => {
+                    hostname = args.split_whitespace().next().map(|name| name.to_string())
+                }
+                _ => (),
+            }
+        }
+
+        block(&mut aliases, &mut hostname);
+
+        hosts
+    }
+
+    // host,group,label with an optional header line. group defaults
+    // to the import group and label to the host
+    fn csv(text: &str, group: &str) -> Vec<Host> {
+        let mut hosts = Vec::new();
+
+        for (n, line) in text.lines().enumerate() {
+            let fields = Self::csv_fields(line);
+
+            match fields.first().map(|host| host.as_str()) {
+                None | Some("") => continue,
+                Some("host") if n == 0 => continue,
+                Some(host) => {
+                    let group = match fields.get(1) {
+                        Some(group) if !group.is_empty() => group,
+                        _ => group,
+                    };
+
+                    let label = match fields.get(2) {
+                        Some(label) if !label.is_empty() => label,
+                        _ => host,
+                    };
+
+                    hosts.push(Self::host(host, group, label))
+                }
+            }
+        }
+
+        hosts
+    }
+
+    // sntop takes each host as three lines, the label it displays,
+    // the host name or address, and a comment, with # comment lines.
+    // entries may also be separated by blank lines
+    fn sntop(text: &str, group: &str) -> Vec<Host> {
+        let mut hosts = Vec::new();
+        let lines: Vec<&str> = text
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.starts_with('#'))
+            .collect();
+
+        for block in lines.split(|line| line.is_empty()) {
+            for entry in block.chunks(3) {
+                match entry {
+                    [label, host, comment] => hosts.push(Host {
+                        comment: comment.to_string(),
+                        ..Self::host(host, group, label)
+                    }),
+                    [label, host] => hosts.push(Self::host(host, group, label)),
+                    _ => (),
+                }
+            }
+        }
+
+        hosts
+    }
+
+    // the hosts nmap found up, named by their first host name or
+    // their address, with their open tcp ports as candidate probes
+    fn nmap(text: &str, group: Option<&str>) -> io::Result<Vec<Host>> {
+        let doc = match roxmltree::Document::parse(text) {
+            Ok(doc) => doc,
+            Err(e) => return Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+        };
+
+        let mut hosts = Vec::new();
+
+        for node in doc.descendants().filter(|node| node.has_tag_name("host")) {
+            let child = |name: &str| node.children().find(|child| child.has_tag_name(name));
+
+            match child("status").and_then(|status| status.attribute("state")) {
+                Some("up") => (),
+                _ => continue,
+            }
+
+            let addr = match node
+                .children()
+                .filter(|child| child.has_tag_name("address"))
+                .find(|address| address.attribute("addrtype") != Some("mac"))
+                .and_then(|address| address.attribute("addr"))
+            {
+                Some(addr) => addr,
+                None => continue,
+            };
+
+            let hostname = child("hostnames").and_then(|hostnames| {
+                hostnames
+                    .children()
+                    .find(|hostname| hostname.has_tag_name("hostname"))
+                    .and_then(|hostname| hostname.attribute("name"))
+            });
+
+            let probes: Vec<Probe> = match child("ports") {
+                Some(ports) => ports
+                    .children()
+                    .filter(|port| {
+                        port.has_tag_name("port")
+                            && port.attribute("protocol") == Some("tcp")
+                            && port
+                                .children()
+                                .find(|state| state.has_tag_name("state"))
+                                .and_then(|state| state.attribute("state"))
+                                == Some("open")
+                    })
+                    .filter_map(|port| port.attribute("portid")?.parse().ok())
+                    .map(|port| Probe::Tcp { port })
+                    .collect(),
+                None => Vec::new(),
+            };
+
+            let (host, label) = match hostname {
+                Some(name) => (name, name.split('.').next().unwrap_or(name)),
+                None => (addr, addr),
+            };
+
+            let group = match group {
+                Some(group) => group.to_string(),
+                None => Self::subnet(addr),
+            };
+
+            hosts.push(Host {
+                probes,
+                ..Self::host(host, &group, label)
+            })
+        }
+
+        Ok(hosts)
+    }
+
+    // the /24 or /64 an address is in, as a group name. a / would
+    // make it a subgroup, so the prefix length follows a -
+    fn subnet(addr: &str) -> String {
+        match addr.parse::<IpAddr>() {
+            Ok(IpAddr::V4(v4)) => {
+                let [a, b, c, _] = v4.octets();
+
+                format!("{}.{}.{}.0-24", a, b, c)
+            }
+            Ok(IpAddr::V6(v6)) => {
+                let [a, b, c, d, ..] = v6.segments();
+
+                format!("{:x}:{:x}:{:x}:{:x}::-64", a, b, c, d)
+            }
+            Err(_) => Self::DEFAULT_GROUP.to_string(),
+        }
+    }
+
+    // comma separated fields, double quotes around a field
+    // protect commas and "" is a literal quote
+    fn csv_fields(line: &str) -> Vec<String> {
+        let mut fields = Vec::new();
+        let mut field = String::new();
+        let mut quoted = false;
+        let mut chars = line.chars().peekable();
+
+        while let Some(ch) = chars.next() {
+            match ch {
+                '"' if quoted && chars.peek() == Some(&'"') => {
+                    field.push('"');
+                    chars.next();
+                }
+                '"' => quoted = !quoted,
+                ',' if !quoted => fields.push(std::mem::take(&mut field)),
+                _ => field.push(ch),
+            }
+        }
+        fields.push(field);
+
+        fields
+            .iter()
+            .map(|field| field.trim().to_string())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // host, group, label
+    fn names(hosts: &[Host]) -> Vec<(&str, &str, &str)> {
+        hosts
+            .iter()
+            .map(|host| (host.host.as_str(), host.group.as_str(), host.label.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn csv() {
+        let text = "host,group,label
+nas.lan,home,nas
+\"db, primary.lan\",,\"say \"\"db\"\"\"
+
+router.lan
+";
+        let hosts = Import::Csv.parse(text, None).unwrap();
+
+        assert_eq!(
+            names(&hosts),
+            [
+                ("nas.lan", "home", "nas"),
+                ("db, primary.lan", "imported", "say \"db\""),
+                ("router.lan", "imported", "router.lan")
+            ]
+        );
+    }
+
+    #[test]
+    fn sntop() {
+        let text = "# sntoprc
+NAS
+nas.lan
+the file server
+
+Router
+192.168.1.1
+gateway
+Printer
+printer.lan
+";
+        let hosts = Import::Sntop.parse(text, None).unwrap();
+
+        assert_eq!(
+            names(&hosts),
+            [
+                ("nas.lan", "sntop", "NAS"),
+                ("192.168.1.1", "sntop", "Router"),
+                ("printer.lan", "sntop", "Printer")
+            ]
+        );
+        assert_eq!(hosts[0].comment, "the file server");
+        assert_eq!(hosts[2].comment, "");
+    }
+
+    #[test]
+    fn nmap() {
+        let text = r#"<?xml version="1.0"?>
+<nmaprun>
+  <host>
+    <status state="up"/>
+    <address addr="192.168.1.10" addrtype="ipv4"/>
+    <address addr="00:11:22:33:44:55" addrtype="mac"/>
+    <hostnames><hostname name="nas.lan" type="PTR"/></hostnames>
+    <ports>
+      <port protocol="tcp" portid="22"><state state="open"/></port>
+      <port protocol="tcp" portid="80"><state state="closed"/></port>
+      <port protocol="udp" portid="53"><state state="open"/></port>
+      <port protocol="tcp" portid="443"><state state="open"/></port>
+    </ports>
+  </host>
+  <host>
+    <status state="down"/>
+    <address addr="192.168.1.11" addrtype="ipv4"/>
+  </host>
+  <host>
+    <status state="up"/>
+    <address addr="fd00:1:2:3::9" addrtype="ipv6"/>
+  </host>
+</nmaprun>
+"#;
+        let hosts = Import::Nmap.parse(text, None).unwrap();
+
+        assert_eq!(
+            names(&hosts),
+            [
+                ("nas.lan", "192.168.1.0-24", "nas"),
+                ("fd00:1:2:3::9", "fd00:1:2:3::-64", "fd00:1:2:3::9")
+            ]
+        );
+        assert_eq!(
+            hosts[0].probes,
+            [Probe::Tcp { port: 22 }, Probe::Tcp { port: 443 }]
+        );
+        assert_eq!(
+            names(&Import::Nmap.parse(text, Some("lab")).unwrap())[0],
+            ("nas.lan", "lab", "nas")
+        );
+        assert!(Import::Nmap.parse("<nmaprun>", None).is_err());
+    }
+
+    #[test]
+    fn merge_skips_hosts_already_present() {
+        let mut hosts = Import::Csv.parse("nas.lan\n", None).unwrap();
+        let added = Import::merge(
+            &mut hosts,
+            Import::Csv.parse("NAS.lan\nrouter.lan\n", None).unwrap(),
+        );
+
+        assert_eq!(added, 1);
+        assert_eq!(hosts.len(), 2);
+    }
+}
@@ -0,0 +1,436 @@
+//  SPDX-FileCopyrightText: Copyright 2023 James M. Putnam (putnamjm.design@gmail.com)
+//  SPDX-License-Identifier: MIT
+
+// maintenance windows and silences
+//
+// windows are listed in settings, for a host by its host name, a
+// group and its subgroups, or every host when neither is given.
+// a window is one-off, from start to end in local time, or
+// recurring, from each time a cron schedule matches for minutes
+//
+//     "maintenance": [
+//         { "group": "dc1", "start": "2023-06-01 22:00", "end": "2023-06-02 02:00" },
+//         { "host": "nas.lan", "cron": "0 3 * * 0", "minutes": 90 }
+//     ]
+//
+// the cron schedule is minute, hour, day of month, month and day
+// of week, 0 or 7 for sunday, each a *, a number, a range, a list
+// of them, or any of them with a /step. as in cron, a day is
+// matched by either day field when neither starts with *, and by
+// both otherwise. whether a host is in a window is worked out
+// once a poll. a host can also be silenced for some minutes from
+// its detail panel. silences are saved as silences.json in the
+// state directory. hosts in a window or silenced are marked, and
+// their alerts aren't run
+use {
+    super::{group::Group, host::Host},
+    crate::{interpolate::Interpolate, Environment},
+    chrono::{DateTime, Datelike, Local, NaiveDateTime, TimeZone, Timelike},
+    serde::{Deserialize, Serialize},
+    std::{collections::HashSet, path::PathBuf, sync::RwLock},
+};
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Maintenance {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cron: Option<String>,
+    #[serde(default)]
+    pub minutes: i64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Silence {
+    pub host: String,
+    // unix seconds
+    pub until: i64,
+}
+
+// the values each cron field matches
+#[derive(Clone, Debug)]
+struct Cron {
+    minutes: Vec<u32>,
+    hours: Vec<u32>,
+    days: Vec<u32>,
+    months: Vec<u32>,
+    weekdays: Vec<u32>,
+    // cron matches either day field when both are restricted, and a
+    // field is only restricted when it doesn't start with *
+    any_day: bool,
+    any_weekday: bool,
+}
+
+#[derive(Clone, Debug)]
+enum Span {
+    Once(DateTime<Local>, DateTime<Local>),
+    Every(Cron, i64),
+}
+
+#[derive(Clone, Debug)]
+struct Window {
+    host: Option<String>,
+    group: Option<String>,
+    span: Span,
+}
+
+impl Maintenance {
+    const TIME_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+    // expand ${VAR} and file: in the string fields
+    pub fn interpolate(&mut self) -> Result<(), String> {
+        for field in [
+            &mut self.host,
+            &mut self.group,
+            &mut self.start,
+            &mut self.end,
+            &mut self.cron,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            Interpolate::field(field)?
+        }
+
+        Ok(())
+    }
+
+    fn time(text: &str) -> Result<DateTime<Local>, String> {
+        NaiveDateTime::parse_from_str(text, Self::TIME_FORMAT)
+            .ok()
+            .and_then(|time| Local.from_local_datetime(&time).earliest())
+            .ok_or_else(|| format!("{}: expected YYYY-MM-DD hh:mm", text))
+    }
+
+    fn window(&self) -> Result<Window, String> {
+        let span = match (&self.start, &self.end, &self.cron) {
+            (Some(start), Some(end), None) => Span::Once(Self::time(start)?, Self::time(end)?),
+            (None, None, Some(cron)) if self.minutes > 0 => {
+                Span::Every(Cron::parse(cron)?, self.minutes)
+            }
+            (None, None, Some(_)) => return Err("a cron window needs minutes".to_string()),
+            _ => return Err("a window needs a start and end, or a cron and minutes".to_string()),
+        };
+
+        Ok(Window {
+            host: self.host.clone(),
+            group: self.group.clone(),
+            span,
+        })
+    }
+}
+
+impl Cron {
+    fn parse(text: &str) -> Result<Cron, String> {
+        let fields: Vec<&str> = text.split_whitespace().collect();
+
+        if fields.len() != 5 {
+            return Err(format!("{}: expected five cron fields", text));
+        }
+
+        let field = |n: usize, min: u32, max: u32| {
+            Self::field(fields[n], min, max).map_err(|e| format!("{}: {}", text, e))
+        };
+
+        Ok(Cron {
+            minutes: field(0, 0, 59)?,
+            hours: field(1, 0, 23)?,
+            days: field(2, 1, 31)?,
+            months: field(3, 1, 12)?,
+            weekdays: field(4, 0, 7)?
+                .into_iter()
+                .map(|weekday| weekday % 7)
+                .collect(),
+            any_day: fields[2].starts_with('*'),
+            any_weekday: fields[4].starts_with('*'),
+        })
+    }
+
+    fn field(text: &str, min: u32, max: u32) -> Result<Vec<u32>, String> {
+        let number = |text: &str| match text.parse::<u32>() {
+            Ok(n) if (min..=max).contains(&n) => Ok(n),
+            _ => Err(format!("`{}` isn't {} to {}", text, min, max)),
+        };
+
+        let mut values = Vec::new();
+
+        for part in text.split(',') {
+            let (range, step) = match part.split_once('/') {
+                Some((range, step)) => match step.parse::<u32>() {
+                    Ok(step) if step > 0 => (range, step),
+                    _ => return Err(format!("`{}` isn't a step", step)),
+                },
+                None => (part, 1),
+            };
+
+            let (from, to) = match range {
+                "*" => (min, max),
+                range => match range.split_once('-') {
+                    Some((from, to)) => (number(from)?, number(to)?),
+                    None if step > 1 => (number(range)?, max),
+                    None => (number(range)?, number(range)?),
+                },
+            };
+
+            values.extend((from..=to).step_by(step as usize))
+        }
+
+        Ok(values)
+    }
+
+    fn matches(&self, time: &DateTime<Local>) -> bool {
+        let day = self.days.contains(&time.day());
+        let weekday = self
+            .weekdays
+            .contains(&time.weekday().num_days_from_sunday());
+
+        self.minutes.contains(&time.minute())
+            && self.hours.contains(&time.hour())
+            && self.months.contains(&time.month())
+            && match (self.any_day, self.any_weekday) {
+                (false, false) => day || weekday,
+                _ => day && weekday,
+            }
+    }
+}
+
+impl Window {
+    fn applies(&self, host: &Host) -> bool {
+        self.host.as_ref().is_none_or(|name| name == &host.host)
+            && self
+                .group
+                .as_ref()
+                .is_none_or(|group| Group::contains(group, &host.group))
+    }
+
+    fn active(&self, now: &DateTime<Local>) -> bool {
+        match &self.span {
+            Span::Once(start, end) => start <= now && now < end,
+            Span::Every(cron, minutes) => (0..*minutes)
+                .map(|minute| *now - chrono::Duration::minutes(minute))
+                .any(|time| cron.matches(&time)),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Schedule {
+    windows: Vec<Window>,
+    // the host names in a window, as of the last poll
+    in_windows: RwLock<HashSet<String>>,
+    silences: RwLock<Vec<Silence>>,
+    path: PathBuf,
+}
+
+impl Schedule {
+    const FILE: &str = "silences.json";
+
+    // windows that don't parse are reported and left out
+    pub fn new(env: &Environment) -> Self {
+        let windows = match &env.settings {
+            Some(settings) => settings
+                .maintenance
+                .iter()
+                .filter_map(|maintenance| match maintenance.window() {
+                    Ok(window) => Some(window),
+                    Err(e) => {
+                        eprintln!("maintenance: {}", e);
+                        None
+                    }
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+
+        let path = env.state_path.join(Self::FILE);
+        let silences = match std::fs::read_to_string(&path) {
+            Ok(json) => serde_json::from_str(&json).unwrap_or_default(),
+            Err(_) => Vec::new(),
+        };
+
+        Schedule {
+            windows,
+            in_windows: RwLock::new(HashSet::new()),
+            silences: RwLock::new(silences),
+            path,
+        }
+    }
+
+    // work out which hosts are in a window, once a poll. which
+    // windows are open doesn't depend on the host, so that's worked
+    // out first
+    pub fn refresh(&self, hosts: &[Host]) {
+        let now = Local::now();
+        let active: Vec<&Window> = self
+            .windows
+            .iter()
+            .filter(|window| window.active(&now))
+            .collect();
+
+        *self.in_windows.write().unwrap() = hosts
+            .iter()
+            .filter(|host| active.iter().any(|window| window.applies(host)))
+            .map(|host| host.host.clone())
+            .collect()
+    }
+
+    // as of the last poll
+    pub fn in_maintenance(&self, host: &Host) -> bool {
+        self.in_windows.read().unwrap().contains(&host.host)
+    }
+
+    pub fn silenced_until(&self, host: &Host) -> Option<DateTime<Local>> {
+        let now = Local::now().timestamp();
+
+        self.silences
+            .read()
+            .unwrap()
+            .iter()
+            .find(|silence| silence.host == host.host && silence.until > now)
+            .and_then(|silence| Local.timestamp_opt(silence.until, 0).single())
+    }
+
+    // silence a host for some minutes from now, or until now to
+    // lift its silence
+    pub fn silence(&self, host: &Host, minutes: i64) -> std::io::Result<()> {
+        let now = Local::now().timestamp();
+
+        {
+            let mut silences = self.silences.write().unwrap();
+
+            silences.retain(|silence| silence.host != host.host && silence.until > now);
+            if minutes > 0 {
+                silences.push(Silence {
+                    host: host.host.clone(),
+                    until: now + minutes * 60,
+                })
+            }
+        }
+
+        self.save()
+    }
+
+    fn save(&self) -> std::io::Result<()> {
+        let silences = self.silences.read().unwrap();
+
+        match serde_json::to_string(&*silences) {
+            Ok(json) => std::fs::write(&self.path, json),
+            Err(e) => Err(std::io::Error::new(std::io::ErrorKind::InvalidData, e)),
+        }
+    }
+
+    // "maintenance" or "silenced" for the hosts that are
+    pub fn mark(&self, host: &Host) -> Option<&'static str> {
+        if self.in_maintenance(host) {
+            Some("maintenance")
+        } else if self.silenced_until(host).is_some() {
+            Some("silenced")
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 2023-06-04 was a sunday
+    fn time(day: u32, hour: u32, minute: u32) -> DateTime<Local> {
+        Local
+            .with_ymd_and_hms(2023, 6, day, hour, minute, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn cron_fields() {
+        assert_eq!(Cron::field("*", 0, 5).unwrap(), [0, 1, 2, 3, 4, 5]);
+        assert_eq!(Cron::field("1,3-5", 0, 59).unwrap(), [1, 3, 4, 5]);
+        assert_eq!(Cron::field("*/15", 0, 59).unwrap(), [0, 15, 30, 45]);
+        assert_eq!(Cron::field("10-20/5", 0, 59).unwrap(), [10, 15, 20]);
+        assert_eq!(Cron::field("50/5", 0, 59).unwrap(), [50, 55]);
+        assert!(Cron::field("60", 0, 59).is_err());
+        assert!(Cron::field("*/0", 0, 59).is_err());
+        assert!(Cron::field("a", 0, 59).is_err());
+    }
+
+    #[test]
+    fn cron_parse() {
+        assert_eq!(Cron::parse("0 3 * * 7").unwrap().weekdays, [0]);
+        assert!(Cron::parse("0 3 * *").is_err());
+        assert!(Cron::parse("0 24 * * *").is_err());
+    }
+
+    #[test]
+    fn cron_days() {
+        // either day field when both are restricted
+        let either = Cron::parse("0 3 1 * 0").unwrap();
+        assert!(either.matches(&time(1, 3, 0)));
+        assert!(either.matches(&time(4, 3, 0)));
+        assert!(!either.matches(&time(5, 3, 0)));
+
+        // both otherwise
+        let both = Cron::parse("0 3 * * 0").unwrap();
+        assert!(both.matches(&time(4, 3, 0)));
+        assert!(!both.matches(&time(1, 3, 0)));
+        assert!(!both.matches(&time(4, 3, 1)));
+    }
+
+    #[test]
+    fn windows() {
+        let every = Maintenance {
+            cron: Some("0 3 * * 0".to_string()),
+            minutes: 90,
+            ..Default::default()
+        }
+        .window()
+        .unwrap();
+        assert!(!every.active(&time(4, 2, 59)));
+        assert!(every.active(&time(4, 3, 0)));
+        assert!(every.active(&time(4, 4, 29)));
+        assert!(!every.active(&time(4, 4, 30)));
+
+        let once = Maintenance {
+            start: Some("2023-06-01 22:00".to_string()),
+            end: Some("2023-06-02 02:00".to_string()),
+            ..Default::default()
+        }
+        .window()
+        .unwrap();
+        assert!(once.active(&time(1, 23, 0)));
+        assert!(!once.active(&time(2, 2, 0)));
+
+        let no_minutes = Maintenance {
+            cron: Some("0 3 * * 0".to_string()),
+            ..Default::default()
+        };
+        assert!(no_minutes.window().is_err());
+    }
+
+    #[test]
+    fn windows_apply_by_host_and_group() {
+        let window = Maintenance {
+            group: Some("dc1".to_string()),
+            cron: Some("* * * * *".to_string()),
+            minutes: 1,
+            ..Default::default()
+        }
+        .window()
+        .unwrap();
+        let host = |group: &str| Host {
+            host: "db1".to_string(),
+            group: group.to_string(),
+            ..Default::default()
+        };
+
+        assert!(window.applies(&host("dc1")));
+        assert!(window.applies(&host("dc1/rack2")));
+        assert!(!window.applies(&host("dc2")));
+    }
+}
@@ -1,6 +1,21 @@
 //  SPDX-FileCopyrightText: Copyright 2023 James M. Putnam (putnamjm.design@gmail.com)
 //  SPDX-License-Identifier: MIT
-mod host;
+pub mod alert;
+pub mod burst;
+pub mod depend;
+pub mod discover;
+pub mod dns;
+pub mod flap;
+pub mod group;
+pub mod history;
+pub mod host;
+pub mod import;
+pub mod maintenance;
+pub mod nagios;
+pub mod probe;
 pub mod snitch_ui;
 mod style;
+pub mod tls;
+pub mod traceroute;
 mod tty;
+pub mod validate;
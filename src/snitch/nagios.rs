@@ -0,0 +1,357 @@
+//  SPDX-FileCopyrightText: Copyright 2023 James M. Putnam (putnamjm.design@gmail.com)
+//  SPDX-License-Identifier: MIT
+
+// nagios plugin mode
+//
+// poll one host, by host name or label, or every host in a group,
+// and print a single status line with perfdata
+//
+//     RSNITCH OK - nas.lan up, rtt 1.204 ms, loss 0% | rtt=1.204ms;50; loss=0%;20;100
+//
+// the exit code is the plugin's, 0 ok, 1 warning, 2 critical and
+// 3 unknown. a host that is down or unreachable is critical, a
+// degraded one a warning, and a group takes the worst of its hosts.
+// hosts are polled with their parents, which aren't reported
+use {
+    super::{
+        burst::Burst,
+        depend::Depend,
+        group::Group,
+        host::{Host, Poll, State, Thresholds},
+        probe::Check,
+    },
+    crate::settings::Ping,
+};
+
+pub struct Nagios {}
+
+impl Nagios {
+    pub const OK: i32 = 0;
+    pub const WARNING: i32 = 1;
+    pub const CRITICAL: i32 = 2;
+    pub const UNKNOWN: i32 = 3;
+
+    fn status(code: i32) -> &'static str {
+        match code {
+            Self::OK => "OK",
+            Self::WARNING => "WARNING",
+            Self::CRITICAL => "CRITICAL",
+            _ => "UNKNOWN",
+        }
+    }
+
+    pub fn code(state: State) -> i32 {
+        match state {
+            State::Up => Self::OK,
+            State::Degraded => Self::WARNING,
+            State::Down | State::Unreachable => Self::CRITICAL,
+            State::Unknown => Self::UNKNOWN,
+        }
+    }
+
+    // critical over warning over unknown over ok
+    fn worst(codes: impl Iterator<Item = i32>) -> i32 {
+        let rank = |code: i32| match code {
+            Self::CRITICAL => 3,
+            Self::WARNING => 2,
+            Self::UNKNOWN => 1,
+            _ => 0,
+        };
+
+        codes
+            .max_by_key(|code| rank(*code))
+            .unwrap_or(Self::UNKNOWN)
+    }
+
+    // the status line and exit code. warn and critical are round
+    // trip limits in milliseconds, warn by default the host's rtt_ms
+    pub fn check(
+        poll: &Poll,
+        ping: &Ping,
+        hosts: &[Host],
+        name: &str,
+        warn: Option<f64>,
+        critical: Option<f64>,
+    ) -> (String, i32) {
+        let selected: Vec<Host> = match hosts
+            .iter()
+            .find(|host| host.host == name || host.label == name)
+        {
+            Some(host) => vec![host.clone()],
+            None => hosts
+                .iter()
+                .filter(|host| !name.is_empty() && Group::contains(name, &host.group))
+                .cloned()
+                .collect(),
+        };
+
+        if selected.is_empty() {
+            return (
+                format!("RSNITCH UNKNOWN - {}: no such host or group", name),
+                Self::UNKNOWN,
+            );
+        }
+
+        // the selected hosts' parents, and theirs, are polled with
+        // them, so a host behind a down router is unreachable
+        let mut polled = selected.clone();
+        let mut next = 0;
+        while next < polled.len() {
+            for parent in Depend::parents(&polled[next], hosts) {
+                if !polled.iter().any(|host| host.host == hosts[parent].host) {
+                    polled.push(hosts[parent].clone())
+                }
+            }
+            next += 1;
+        }
+
+        let mut states = poll.poll_all(&polled);
+        states.truncate(selected.len());
+        let codes: Vec<i32> = selected
+            .iter()
+            .zip(&states)
+            .map(|(host, state)| {
+                let warn = warn.or(host.thresholds.rtt_ms);
+                let rtt = poll
+                    .burst(host)
+                    .and_then(|burst| burst.avg())
+                    .map(Thresholds::millis);
+
+                Self::worst(
+                    [
+                        Self::code(*state),
+                        match (rtt, critical, warn) {
+                            (Some(rtt), Some(critical), _) if rtt > critical => Self::CRITICAL,
+                            (Some(rtt), _, Some(warn)) if rtt > warn => Self::WARNING,
+                            _ => Self::OK,
+                        },
+                    ]
+                    .into_iter(),
+                )
+            })
+            .collect();
+        let code = Self::worst(codes.iter().copied());
+
+        let (summary, perfdata) = match selected.as_slice() {
+            [host] => {
+                let burst = poll.burst(host);
+                let checks = poll.checks(host);
+                let mut summary = format!("{} {}", host.host, states[0].as_str());
+
+                if let Some(burst) = &burst {
+                    if let Some(avg) = burst.avg() {
+                        summary.push_str(&format!(", rtt {:.3} ms", Thresholds::millis(avg)))
+                    }
+                    summary.push_str(&format!(", loss {:.0}%", burst.loss_pct()))
+                }
+
+                for (probe, check) in host.probes.iter().zip(&checks) {
+                    summary.push_str(&format!(", {} {}", probe, check))
+                }
+
+                let mut perfdata = Self::perfdata(
+                    "",
+                    burst.as_ref(),
+                    ping,
+                    warn.or(host.thresholds.rtt_ms),
+                    critical,
+                );
+                perfdata.extend(Self::probe_perfdata(host, &checks));
+
+                (host.templates.redact(&summary), perfdata)
+            }
+            _ => {
+                let count = |state| State::count(&states, state);
+                let down: Vec<&str> = selected
+                    .iter()
+                    .zip(&codes)
+                    .filter(|(_, code)| **code != Self::OK)
+                    .map(|(host, _)| host.label.as_str())
+                    .collect();
+
+                let mut summary = format!(
+                    "{}: {} up, {} degraded, {} down, {} unreachable, {} unknown",
+                    name,
+                    count(State::Up),
+                    count(State::Degraded),
+                    count(State::Down),
+                    count(State::Unreachable),
+                    count(State::Unknown)
+                );
+                if !down.is_empty() {
+                    summary.push_str(&format!(" ({})", down.join(", ")))
+                }
+
+                let perfdata = selected
+                    .iter()
+                    .flat_map(|host| {
+                        Self::perfdata(
+                            &host.label,
+                            poll.burst(host).as_ref(),
+                            ping,
+                            warn.or(host.thresholds.rtt_ms),
+                            critical,
+                        )
+                    })
+                    .collect();
+
+                (summary, perfdata)
+            }
+        };
+
+        // a | in a probe's output would start the perfdata
+        (
+            format!(
+                "RSNITCH {} - {} | {}",
+                Self::status(code),
+                summary.replace('|', "/"),
+                perfdata.join(" ")
+            ),
+            code,
+        )
+    }
+
+    // labels are quoted, with any quote in them doubled
+    fn label(label: &str) -> String {
+        format!("'{}'", label.replace('\'', "''"))
+    }
+
+    // 'label'=value[uom];[warn];[crit], U when there's no value
+    fn perfdata(
+        prefix: &str,
+        burst: Option<&Burst>,
+        ping: &Ping,
+        warn: Option<f64>,
+        critical: Option<f64>,
+    ) -> Vec<String> {
+        let label = |name: &str| match prefix.is_empty() {
+            true => name.to_string(),
+            false => Self::label(&format!("{} {}", prefix, name)),
+        };
+        let limit = |limit: Option<f64>| limit.map(|limit| limit.to_string()).unwrap_or_default();
+
+        let rtt = match burst.and_then(|burst| burst.avg()) {
+            Some(avg) => format!("{:.3}ms", Thresholds::millis(avg)),
+            None => "U".to_string(),
+        };
+        let loss = match burst {
+            Some(burst) => format!("{:.0}%", burst.loss_pct()),
+            None => "U".to_string(),
+        };
+
+        vec![
+            format!(
+                "{}={};{};{}",
+                label("rtt"),
+                rtt,
+                limit(warn),
+                limit(critical)
+            ),
+            format!(
+                "{}={};{};{}",
+                label("loss"),
+                loss,
+                ping.degraded_loss,
+                ping.down_loss
+            ),
+        ]
+    }
+
+    fn probe_perfdata(host: &Host, checks: &[Check]) -> Vec<String> {
+        host.probes
+            .iter()
+            .zip(checks)
+            .filter_map(|(probe, check)| {
+                check.elapsed.map(|elapsed| {
+                    format!(
+                        "{}={:.3}ms;{};",
+                        Self::label(&probe.to_string()),
+                        Thresholds::millis(elapsed),
+                        host.thresholds
+                            .probe_ms
+                            .map(|limit| limit.to_string())
+                            .unwrap_or_default()
+                    )
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::snitch::probe::Probe, std::time::Duration};
+
+    fn burst(sent: usize, millis: &[u64]) -> Burst {
+        Burst {
+            sent,
+            rtts: millis.iter().map(|ms| Duration::from_millis(*ms)).collect(),
+        }
+    }
+
+    #[test]
+    fn worst_code() {
+        let worst = |codes: &[i32]| Nagios::worst(codes.iter().copied());
+
+        assert_eq!(worst(&[Nagios::OK, Nagios::UNKNOWN]), Nagios::UNKNOWN);
+        assert_eq!(worst(&[Nagios::UNKNOWN, Nagios::WARNING]), Nagios::WARNING);
+        assert_eq!(
+            worst(&[Nagios::WARNING, Nagios::CRITICAL]),
+            Nagios::CRITICAL
+        );
+        assert_eq!(worst(&[]), Nagios::UNKNOWN);
+        assert_eq!(Nagios::code(State::Unreachable), Nagios::CRITICAL);
+    }
+
+    #[test]
+    fn host_perfdata() {
+        let burst = burst(4, &[1, 2, 3]);
+
+        assert_eq!(
+            Nagios::perfdata("", Some(&burst), &Ping::default(), Some(50.0), None),
+            ["rtt=2.000ms;50;", "loss=25%;20;100"]
+        );
+        assert_eq!(
+            Nagios::perfdata("", None, &Ping::default(), None, Some(200.0)),
+            ["rtt=U;;200", "loss=U;20;100"]
+        );
+    }
+
+    #[test]
+    fn group_perfdata_labels() {
+        assert_eq!(
+            Nagios::perfdata(
+                "bob's nas",
+                Some(&burst(2, &[])),
+                &Ping::default(),
+                None,
+                None
+            ),
+            ["'bob''s nas rtt'=U;;", "'bob''s nas loss'=100%;20;100"]
+        );
+    }
+
+    #[test]
+    fn probe_perfdata_skips_probes_without_a_time() {
+        let host = Host {
+            probes: vec![Probe::Tcp { port: 22 }, Probe::Tcp { port: 443 }],
+            thresholds: Thresholds {
+                probe_ms: Some(100.0),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let checks = [
+            Check {
+                elapsed: Some(Duration::from_micros(2104)),
+                ..Default::default()
+            },
+            Check::default(),
+        ];
+
+        assert_eq!(
+            Nagios::probe_perfdata(&host, &checks),
+            ["'tcp/22'=2.104ms;100;"]
+        );
+    }
+}
@@ -0,0 +1,438 @@
+//  SPDX-FileCopyrightText: Copyright 2023 James M. Putnam (putnamjm.design@gmail.com)
+//  SPDX-License-Identifier: MIT
+
+// host probes
+//
+// checks beyond the ping poll, listed per host in hosts.json
+//
+//     "probes": [
+//         { "type": "tcp", "port": 22 },
+//         { "type": "dns", "resolver": "192.168.1.1", "record": "A", "expect": ["192.168.1.10"] }
+//     ]
+//
+// a dns probe asks the resolver, the system's by default, for the
+// host's records of a type, A by default, and is down if it can't
+// answer or leaves out any expected answer.
+//
+//         { "type": "tls", "port": 443, "sni": "www.example.com", "warn_days": 14 }
+//
+// a tls probe reads the server's certificate, and is degraded when
+// it expires within warn_days and down once it has expired
+//
+//         { "type": "command", "command": ["/usr/lib/nagios/plugins/check_ssh", "{host}"], "timeout": 10 }
+//
+// a command probe runs a program, without a shell, with {host} in
+// its arguments replaced by the host name, as the user who ran sudo
+// rather than root. its environment is only PATH, LANG and
+// RSNITCH_HOST. the exit status is read nagios
+// style, 0 up, 1 degraded, 2 down and anything else unknown, as is
+// running out of time, and the first line it prints is the detail.
+// probes are run whether or not the host answers pings
+use {
+    super::{dns::Dns, host::State, tls::Tls},
+    crate::interpolate::Templates,
+    serde::{Deserialize, Serialize},
+    std::{
+        env, fmt,
+        io::{BufRead, BufReader},
+        net::{IpAddr, TcpStream, ToSocketAddrs},
+        os::unix::process::CommandExt,
+        process::{Child, Command, Stdio},
+        sync::mpsc,
+        time::{Duration, Instant},
+    },
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Probe {
+    Tcp {
+        port: u16,
+    },
+    Dns {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        resolver: Option<IpAddr>,
+        #[serde(default = "Probe::default_record")]
+        record: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        expect: Vec<String>,
+    },
+    Tls {
+        #[serde(default = "Probe::default_tls_port")]
+        port: u16,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        sni: Option<String>,
+        #[serde(default = "Probe::default_warn_days")]
+        warn_days: i64,
+    },
+    Command {
+        command: Vec<String>,
+        // seconds
+        #[serde(default = "Probe::default_timeout")]
+        timeout: u64,
+    },
+}
+
+// a probe's state, what it found, and how long it took. lines
+// go under the probe's line in the host detail panel
+#[derive(Debug, Clone, Default)]
+pub struct Check {
+    pub state: State,
+    pub detail: String,
+    pub elapsed: Option<Duration>,
+    pub lines: Vec<String>,
+}
+
+impl fmt::Display for Probe {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Probe::Tcp { port } => write!(f, "tcp/{}", port),
+            Probe::Dns {
+                resolver, record, ..
+            } => match resolver {
+                Some(resolver) => write!(f, "dns/{}@{}", record, resolver),
+                None => write!(f, "dns/{}", record),
+            },
+            Probe::Tls { port, .. } => write!(f, "tls/{}", port),
+            Probe::Command { command, .. } => match command.first() {
+                Some(program) => write!(
+                    f,
+                    "command/{}",
+                    program.rsplit('/').next().unwrap_or(program)
+                ),
+                None => write!(f, "command"),
+            },
+        }
+    }
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.state.as_str())?;
+
+        if let Some(elapsed) = self.elapsed {
+            write!(f, " {:.1} ms", elapsed.as_secs_f64() * 1000.0)?
+        }
+
+        if !self.detail.is_empty() {
+            write!(f, " {}", self.detail)?
+        }
+
+        Ok(())
+    }
+}
+
+impl Probe {
+    pub const TYPES: [&str; 4] = ["tcp", "dns", "tls", "command"];
+    const TIMEOUT: Duration = Duration::from_secs(1);
+    const PATH: &str = "/usr/local/bin:/usr/bin:/bin";
+
+    fn default_record() -> String {
+        "A".to_string()
+    }
+
+    fn default_tls_port() -> u16 {
+        443
+    }
+
+    fn default_warn_days() -> i64 {
+        14
+    }
+
+    fn default_timeout() -> u64 {
+        10
+    }
+
+    // expand ${VAR} and file: in the string fields, keeping their
+    // templates under key, the probe's place in its host
+    pub fn interpolate(&mut self, key: &str, templates: &mut Templates) -> Result<(), String> {
+        let list = |field: &str, n: usize| format!("{}.{}.{}", key, field, n);
+
+        match self {
+            Probe::Tcp { .. } => (),
+            Probe::Dns { name, expect, .. } => {
+                if let Some(name) = name {
+                    templates.field(&format!("{}.name", key), name)?
+                }
+
+                for (n, field) in expect.iter_mut().enumerate() {
+                    templates.field(&list("expect", n), field)?
+                }
+            }
+            Probe::Tls { sni, .. } => {
+                if let Some(sni) = sni {
+                    templates.field(&format!("{}.sni", key), sni)?
+                }
+            }
+            Probe::Command { command, .. } => {
+                for (n, field) in command.iter_mut().enumerate() {
+                    templates.field(&list("command", n), field)?
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // the probe as it was written, before interpolation
+    pub fn restored(&self, key: &str, templates: &Templates) -> Probe {
+        let restore =
+            |field: &str, value: &str| templates.restore(&format!("{}.{}", key, field), value);
+        let restore_list = |field: &str, values: &[String]| -> Vec<String> {
+            values
+                .iter()
+                .enumerate()
+                .map(|(n, value)| restore(&format!("{}.{}", field, n), value))
+                .collect()
+        };
+
+        match self {
+            Probe::Tcp { .. } => self.clone(),
+            Probe::Dns {
+                resolver,
+                record,
+                name,
+                expect,
+            } => Probe::Dns {
+                resolver: *resolver,
+                record: record.clone(),
+                name: name.as_deref().map(|name| restore("name", name)),
+                expect: restore_list("expect", expect),
+            },
+            Probe::Tls {
+                port,
+                sni,
+                warn_days,
+            } => Probe::Tls {
+                port: *port,
+                sni: sni.as_deref().map(|sni| restore("sni", sni)),
+                warn_days: *warn_days,
+            },
+            Probe::Command { command, timeout } => Probe::Command {
+                command: restore_list("command", command),
+                timeout: *timeout,
+            },
+        }
+    }
+
+    pub fn check(&self, host: &str) -> State {
+        self.run(host).state
+    }
+
+    pub fn run(&self, host: &str) -> Check {
+        match self {
+            Probe::Tcp { port } => match (host, *port).to_socket_addrs() {
+                Ok(mut addrs) => match addrs.next() {
+                    Some(addr) => {
+                        let start = Instant::now();
+
+                        match TcpStream::connect_timeout(&addr, Self::TIMEOUT) {
+                            Ok(_) => Check {
+                                state: State::Up,
+                                elapsed: Some(start.elapsed()),
+                                ..Default::default()
+                            },
+                            Err(e) => Check {
+                                state: State::Down,
+                                detail: e.to_string(),
+                                ..Default::default()
+                            },
+                        }
+                    }
+                    None => Check::default(),
+                },
+                Err(e) => Check {
+                    state: State::Unknown,
+                    detail: e.to_string(),
+                    ..Default::default()
+                },
+            },
+            Probe::Dns {
+                resolver,
+                record,
+                name,
+                expect,
+            } => {
+                let name = name.as_deref().unwrap_or(host);
+
+                match Dns::query(*resolver, name, record) {
+                    Ok(answer) => {
+                        let missing: Vec<&String> = expect
+                            .iter()
+                            .filter(|expected| !answer.records.contains(&Dns::normalize(expected)))
+                            .collect();
+
+                        match missing.is_empty() {
+                            true => Check {
+                                state: State::Up,
+                                detail: answer.records.join(" "),
+                                elapsed: Some(answer.elapsed),
+                                ..Default::default()
+                            },
+                            false => Check {
+                                state: State::Down,
+                                detail: format!(
+                                    "got {}, expected {}",
+                                    answer.records.join(" "),
+                                    expect.join(" ")
+                                ),
+                                elapsed: Some(answer.elapsed),
+                                ..Default::default()
+                            },
+                        }
+                    }
+                    Err(e) => Check {
+                        state: State::Down,
+                        detail: e,
+                        ..Default::default()
+                    },
+                }
+            }
+            Probe::Tls {
+                port,
+                sni,
+                warn_days,
+            } => match Tls::certificate(host, *port, sni.as_deref()) {
+                Ok((cert, elapsed)) => {
+                    let days = cert.days_left();
+
+                    Check {
+                        state: if days < 0 {
+                            State::Down
+                        } else if days < *warn_days {
+                            State::Degraded
+                        } else {
+                            State::Up
+                        },
+                        detail: match days {
+                            days if days < 0 => format!("expired {} days ago", -days),
+                            days => format!("expires in {} days", days),
+                        },
+                        elapsed: Some(elapsed),
+                        lines: cert.lines(),
+                    }
+                }
+                Err(e) => Check {
+                    state: State::Down,
+                    detail: e,
+                    ..Default::default()
+                },
+            },
+            Probe::Command { command, timeout } => {
+                let start = Instant::now();
+
+                match Self::command(host, command, Duration::from_secs(*timeout)) {
+                    Ok((code, detail)) => Check {
+                        state: Self::exit_state(code),
+                        detail,
+                        elapsed: Some(start.elapsed()),
+                        ..Default::default()
+                    },
+                    Err(e) => Check {
+                        state: State::Unknown,
+                        detail: e,
+                        ..Default::default()
+                    },
+                }
+            }
+        }
+    }
+
+    // nagios plugin exit codes
+    pub fn exit_state(code: i32) -> State {
+        match code {
+            0 => State::Up,
+            1 => State::Degraded,
+            2 => State::Down,
+            _ => State::Unknown,
+        }
+    }
+
+    // the exit code and first line of output of a command, killed,
+    // with anything it started, if it runs past its timeout
+    fn command(host: &str, command: &[String], timeout: Duration) -> Result<(i32, String), String> {
+        let (program, args) = command.split_first().ok_or("empty command")?;
+
+        let mut child = Self::unprivileged(&mut Command::new(program))
+            .args(args.iter().map(|arg| arg.replace("{host}", host)))
+            .env_clear()
+            .env("PATH", Self::PATH)
+            .env("LANG", "C")
+            .env("RSNITCH_HOST", host)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .process_group(0)
+            .spawn()
+            .map_err(|e| format!("{}: {}", program, e))?;
+
+        // read in a thread, sending the first line as soon as it's
+        // read and draining the rest, a full pipe would stall the
+        // command
+        let stdout = child.stdout.take().ok_or("no stdout")?;
+        let (sender, output) = mpsc::channel();
+        std::thread::spawn(move || {
+            let mut stdout = BufReader::new(stdout);
+            let mut line = String::new();
+
+            stdout.read_line(&mut line).ok();
+            sender.send(line).ok();
+            std::io::copy(&mut stdout, &mut std::io::sink()).ok();
+        });
+
+        let start = Instant::now();
+        let status = loop {
+            match child.try_wait() {
+                Ok(Some(status)) => break status,
+                Ok(None) if start.elapsed() < timeout => {
+                    std::thread::sleep(Duration::from_millis(50))
+                }
+                Ok(None) => {
+                    Self::kill_group(&mut child);
+                    return Err(format!("timed out after {} s", timeout.as_secs()));
+                }
+                Err(e) => return Err(e.to_string()),
+            }
+        };
+
+        // something the command left running in the background can
+        // hold stdout open, so the first line is only waited for
+        // until the timeout
+        let line = match output.recv_timeout(timeout.saturating_sub(start.elapsed())) {
+            Ok(line) => line,
+            Err(_) => {
+                Self::kill_group(&mut child);
+                String::new()
+            }
+        };
+
+        Ok((status.code().unwrap_or(3), line.trim().to_string()))
+    }
+
+    // the command is the leader of its own process group, kill the
+    // whole group so nothing it started outlives it
+    fn kill_group(child: &mut Child) {
+        // safety: killpg only sends a signal
+        unsafe {
+            libc::killpg(child.id() as libc::pid_t, libc::SIGKILL);
+        }
+        child.kill().ok();
+        child.wait().ok();
+    }
+
+    // run as root by sudo, commands run as the user who ran sudo.
+    // std drops root's supplementary groups along with the uid
+    fn unprivileged(command: &mut Command) -> &mut Command {
+        let id = |var| env::var(var).ok().and_then(|id| id.parse::<u32>().ok());
+
+        // safety: geteuid can't fail
+        if unsafe { libc::geteuid() } == 0 {
+            if let (Some(uid), Some(gid)) = (id("SUDO_UID"), id("SUDO_GID")) {
+                command.gid(gid).uid(uid);
+            }
+        }
+        command
+    }
+}
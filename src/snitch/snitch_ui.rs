@@ -7,21 +7,48 @@
 #![allow(dead_code)]
 #![allow(unused_imports)]
 use {
-    super::host::{Host, Poll},
-    crate::Environment,
+    super::{
+        alert::Alerts,
+        discover::Discover,
+        group::{Group, GroupTree},
+        history::History,
+        host::{Host, Poll, State},
+        import::Import,
+        style::state::{self, StatePalette},
+        traceroute::Trace,
+        validate::{Severity, Validation},
+    },
+    crate::{
+        settings::{self, Keys},
+        Environment,
+    },
     iced::{
         alignment::{self, Horizontal, Vertical},
-        executor, subscription, theme,
-        widget::{container, horizontal_rule, row, text, Column, Container, Row, Scrollable, Text},
+        event, executor,
+        keyboard::{self, KeyCode},
+        subscription, theme,
+        widget::{
+            checkbox, container, horizontal_rule, row, text, text_input, Column, Container, Row,
+            Scrollable, Space, Text,
+        },
         window, Alignment, Application, Command, Element, Event, Length, Renderer, Subscription,
         Theme,
     },
     iced_aw::{grid, Grid},
-    std::sync::RwLock,
+    iced_futures::futures::channel::oneshot,
+    once_cell::sync::Lazy,
+    std::{
+        path::PathBuf,
+        sync::{Arc, Mutex, RwLock},
+    },
 };
 
+static SEARCH_ID: Lazy<text_input::Id> = Lazy::new(|| text_input::Id::new("search"));
+
 // components
 #[derive(Debug, Default)]
+// rows lines high, scrolling through up to MAX_LINES, written from
+// the top and a line at a time
 pub struct InfoBox {
     image: RwLock<String>,
     lines: RwLock<Vec<String>>,
@@ -30,10 +57,14 @@ pub struct InfoBox {
 }
 
 impl InfoBox {
+    const MAX_LINES: usize = 200;
+    // of the default text size
+    const LINE_HEIGHT: u16 = 26;
+
     pub fn new(_nv: &Environment, rows: usize, cols: usize) -> Self {
         InfoBox {
             image: RwLock::new(String::new()),
-            lines: RwLock::new(vec![String::new(); rows]),
+            lines: RwLock::new(vec![String::new()]),
             rows,
             cols,
         }
@@ -43,31 +74,21 @@ impl InfoBox {
         let mut image = self.image.write().unwrap();
         let lines = self.lines.read().unwrap();
 
-        let mut img = String::new();
-
-        for line in &lines[0..self.rows - 1] {
-            if line.is_empty() {
-                img.push_str(" \n");
-            } else {
-                img.push_str(line);
-                img.push('\n')
-            }
-        }
-
-        if lines[self.rows - 1].is_empty() {
-            img.push(' ');
-        } else {
-            img.push_str(&lines[self.rows - 1]);
-        }
-
-        *image = img
+        *image = lines
+            .iter()
+            .map(|line| match line.is_empty() {
+                true => " ",
+                false => line.as_str(),
+            })
+            .collect::<Vec<&str>>()
+            .join("\n")
     }
 
     pub fn clear(&self) {
         {
             let mut lines = self.lines.write().unwrap();
 
-            *lines = vec![String::new(); self.rows]
+            *lines = vec![String::new()]
         }
 
         self.collapse()
@@ -77,7 +98,9 @@ impl InfoBox {
         {
             let mut lines = self.lines.write().unwrap();
 
-            lines.remove(0);
+            if lines.len() == Self::MAX_LINES {
+                lines.remove(0);
+            }
             lines.push(String::new())
         }
 
@@ -88,8 +111,8 @@ impl InfoBox {
         {
             let mut lines = self.lines.write().unwrap();
 
-            if !lines[self.rows - 1].is_empty() {
-                lines[self.rows - 1].pop().unwrap();
+            if let Some(line) = lines.last_mut() {
+                line.pop();
             }
         }
 
@@ -100,7 +123,9 @@ impl InfoBox {
         {
             let mut lines = self.lines.write().unwrap();
 
-            lines[self.rows - 1].push(ch)
+            if let Some(line) = lines.last_mut() {
+                line.push(ch)
+            }
         }
 
         self.collapse()
@@ -110,7 +135,9 @@ impl InfoBox {
         {
             let mut lines = self.lines.write().unwrap();
 
-            lines[self.rows - 1].push_str(&str)
+            if let Some(line) = lines.last_mut() {
+                line.push_str(&str)
+            }
         }
 
         self.collapse()
@@ -123,48 +150,55 @@ impl InfoBox {
     }
 
     pub fn view(&self) -> Element<'_, Message, Renderer> {
-        Column::new().push(text(self.contents())).into()
+        Scrollable::new(text(self.contents()))
+            .height(self.rows as u16 * Self::LINE_HEIGHT)
+            .into()
     }
 }
 
 #[derive(Debug, Default)]
 pub struct GroupBox {
-    cols: usize,
+    indent: u16,
 }
 
 impl GroupBox {
-    pub fn new(_env: &Environment, cols: usize) -> Self {
-        GroupBox { cols }
+    pub fn new(_env: &Environment, indent: u16) -> Self {
+        GroupBox { indent }
     }
 
-    pub fn view(&self, groups: &[String]) -> iced_native::Element<'_, Message, Renderer> {
-        let grid_spacer = "                                 ";
+    pub fn view(&self, groups: &GroupTree, states: &[State]) -> Element<'_, Message, Renderer> {
+        let mut group_col = Column::new().align_items(Alignment::Start).spacing(2);
 
-        let mut group_grid = Grid::with_columns(self.cols);
+        for id in groups.visible() {
+            let group = groups.group(id);
+            let (up, down) = groups.counts(id, states);
 
-        for (id, group) in groups.iter().enumerate() {
-            if id % self.cols == 0 {
-                for _ in 0..self.cols {
-                    group_grid.insert(text(grid_spacer));
-                }
-            }
+            let toggle: Element<'_, Message, Renderer> = if group.children == 0 {
+                Space::with_width(20).into()
+            } else {
+                iced::widget::button(text(if groups.is_expanded(id) { "-" } else { "+" }).size(13))
+                    .width(20)
+                    .height(24)
+                    .style(theme::Button::Secondary)
+                    .on_press(Message::GroupToggle(id))
+                    .into()
+            };
 
-            group_grid.insert(
-                iced::widget::button(text(group))
-                    .height(30)
-                    .style(theme::Button::Primary)
-                    .on_press(Message::GroupPress(id)),
+            group_col = group_col.push(
+                row![
+                    Space::with_width(group.depth as u16 * self.indent),
+                    toggle,
+                    iced::widget::button(text(format!("{} {}/{}", group.name, up, down)).size(13))
+                        .height(24)
+                        .style(theme::Button::Primary)
+                        .on_press(Message::GroupPress(id)),
+                ]
+                .spacing(4)
+                .align_items(Alignment::Center),
             );
         }
 
-        let content = Scrollable::new(
-            Column::new()
-                .align_items(Alignment::Start)
-                .spacing(15)
-                .push(group_grid),
-        );
-
-        container(content)
+        container(Scrollable::new(group_col))
             .width(Length::Fill)
             .height(Length::Fill)
             .into()
@@ -174,11 +208,17 @@ impl GroupBox {
 #[derive(Debug, Default)]
 pub struct HostBox {
     cols: usize,
+    palette: StatePalette,
 }
 
 impl HostBox {
-    pub fn new(_env: &Environment, cols: usize) -> Self {
-        HostBox { cols }
+    pub fn new(env: &Environment, cols: usize) -> Self {
+        let palette = match &env.settings {
+            Some(settings) => StatePalette::new(&settings.theme.states),
+            None => StatePalette::default(),
+        };
+
+        HostBox { cols, palette }
     }
 
     pub fn effective_hid(
@@ -195,49 +235,85 @@ impl HostBox {
         }
     }
 
+    // ids of the hosts in the group filter that match the search
+    pub fn visible(&self, filter: &str, search: &str, hosts: &[Host]) -> Vec<usize> {
+        hosts
+            .iter()
+            .enumerate()
+            .filter(|(_, host)| host.matches(filter, search))
+            .map(|(id, _)| id)
+            .collect()
+    }
+
+    // move the cursor over the grid, clamped to the visible hosts
+    pub fn step(
+        &self,
+        cursor: Option<usize>,
+        nhosts: usize,
+        dx: isize,
+        dy: isize,
+    ) -> Option<usize> {
+        if nhosts == 0 {
+            return None;
+        }
+
+        match cursor {
+            Some(cursor) => {
+                let cursor = cursor as isize + dx + dy * self.cols as isize;
+
+                Some(cursor.clamp(0, nhosts as isize - 1) as usize)
+            }
+            None => Some(0),
+        }
+    }
+
     pub fn view(
         &self,
-        filter: String,
+        visible: &[usize],
+        cursor: Option<usize>,
         hosts: &[Host],
-        states: &[bool],
+        states: &[State],
+        notes: &[Vec<&str>],
+        flapping: &[bool],
     ) -> Element<'_, Message, Renderer> {
         let grid_spacer = "                                 ";
 
         let mut host_grid = Grid::with_columns(self.cols);
-        for (id, host) in hosts
-            .iter()
-            .filter(|host| {
-                if filter.is_empty() {
-                    true
-                } else {
-                    host.group == *filter
-                }
-            })
-            .enumerate()
-        {
+        for (id, host_id) in visible.iter().enumerate() {
+            let host = &hosts[*host_id];
+
             if id % self.cols == 0 {
                 for _ in 0..self.cols {
                     host_grid.insert(text(grid_spacer));
                 }
             }
 
-            let host_id = if filter.is_empty() {
-                id
+            let label = match notes.get(*host_id) {
+                Some(notes) if !notes.is_empty() => {
+                    format!("{} ({})", host.label, notes.join(", "))
+                }
+                _ => host.label.clone(),
+            };
+            let flapping = flapping.get(*host_id) == Some(&true);
+            let label = match flapping {
+                true => format!("{} ~", label),
+                false => label,
+            };
+            let label = if cursor == Some(id) {
+                format!("[{}]", label)
             } else {
-                hosts
-                    .iter()
-                    .position(|host_| host_.host == host.host)
-                    .unwrap()
+                label
             };
 
+            let state = states[*host_id];
+
             host_grid.insert(
-                iced::widget::button(text(&host.label))
-                    .style(if states[host_id] {
-                        theme::Button::Primary
-                    } else {
-                        theme::Button::Secondary
+                iced::widget::button(row![state::icon(state), text(label)].spacing(6))
+                    .style(match flapping {
+                        true => self.palette.flapping(state),
+                        false => self.palette.button(state),
                     })
-                    .on_press(Message::HostPress(host_id)),
+                    .on_press(Message::HostPress(*host_id)),
             );
         }
 
@@ -262,16 +338,56 @@ pub struct StatusBar {
 
 impl StatusBar {
     pub fn new(env: &Environment) -> Self {
-        let host_path = match &env.hosts_path {
-            Some(path) => path.to_str().unwrap().to_string(),
-            None => "~/.rsnitch-rs/hosts.json".to_string(),
-        };
+        StatusBar {
+            host_path: Host::path(env).display().to_string(),
+        }
+    }
 
-        StatusBar { host_path }
+    fn clock(time: &time::OffsetDateTime) -> String {
+        format!(
+            "{:02}:{:02}:{:02}",
+            time.hour(),
+            time.minute(),
+            time.second()
+        )
     }
 
-    pub fn view(&self, filter: String) -> Element<Message> {
+    pub fn view(
+        &self,
+        filter: String,
+        search: &str,
+        states: &[State],
+        dns_failing: usize,
+        last_poll: Option<time::OffsetDateTime>,
+        next_poll_secs: u64,
+    ) -> Element<Message> {
+        let summary = text(format!(
+            "{} up / {} degraded / {} down{} / {} unknown{}    last poll: {}    next poll: {}:{:02}",
+            State::count(states, State::Up),
+            State::count(states, State::Degraded),
+            State::count(states, State::Down),
+            match State::count(states, State::Unreachable) {
+                0 => String::new(),
+                n => format!(" / {} unreachable", n),
+            },
+            State::count(states, State::Unknown),
+            match dns_failing {
+                0 => String::new(),
+                n => format!(" / {} dns failing", n),
+            },
+            match &last_poll {
+                Some(time) => Self::clock(time),
+                None => "never".to_string(),
+            },
+            next_poll_secs / 60,
+            next_poll_secs % 60,
+        ))
+        .size(14);
         let filter = text(format!("filter: {}", filter)).size(20);
+        let search = text_input("search", search, Message::Search)
+            .id(SEARCH_ID.clone())
+            .size(16)
+            .width(120);
         let host_path = text(self.host_path.clone()).size(20);
         let buttons = row![
             iced::widget::button(text("clear".to_string()).size(13))
@@ -281,7 +397,11 @@ impl StatusBar {
             iced::widget::button(text("refresh".to_string()).size(13))
                 .height(28)
                 .style(theme::Button::Primary)
-                .on_press(Message::Poll)
+                .on_press(Message::Poll),
+            iced::widget::button(text("discover".to_string()).size(13))
+                .height(28)
+                .style(theme::Button::Primary)
+                .on_press(Message::Discover)
         ]
         .spacing(8);
 
@@ -290,44 +410,568 @@ impl StatusBar {
             .spacing(6)
             .push(host_path.width(Length::Fill))
             .push(filter.width(Length::Fill))
+            .push(search)
             .push(buttons.width(Length::Shrink));
 
-        container(content)
+        container(Column::new().spacing(2).push(content).push(summary))
             .width(Length::Fill)
             .height(Length::Fill)
             .into()
     }
 }
 
+// subnet discovery dialog
+#[derive(Debug, Default)]
+pub struct DiscoverBox {
+    cidr: String,
+    group: String,
+    candidates: Vec<(Host, bool)>,
+    status: String,
+    sweeping: bool,
+}
+
+impl DiscoverBox {
+    pub fn new() -> Self {
+        DiscoverBox {
+            cidr: String::new(),
+            group: Import::DEFAULT_GROUP.to_string(),
+            candidates: Vec::new(),
+            status: String::new(),
+            sweeping: false,
+        }
+    }
+
+    // sweep in a thread, with the sweeper's own pinger so its
+    // replies don't mix with a poll's, and deliver the hosts found
+    pub fn sweep(&mut self, sweeper: &Arc<Mutex<Poll>>) -> Command<Message> {
+        let (cidr, group) = (self.cidr.clone(), self.group.clone());
+        let sweeper = sweeper.clone();
+        let (found, receiver) = oneshot::channel();
+
+        self.sweeping = true;
+        self.status = format!("sweeping {}", cidr);
+
+        std::thread::spawn(move || {
+            let poll = sweeper.lock().unwrap();
+
+            found.send(Discover::sweep(&poll, &cidr, &group)).ok();
+        });
+
+        Command::perform(
+            async move {
+                receiver
+                    .await
+                    .unwrap_or_else(|_| Err("sweep failed".to_string()))
+            },
+            Message::DiscoverFound,
+        )
+    }
+
+    // offer the live hosts not already in hosts
+    pub fn found(&mut self, found: Result<Vec<Host>, String>, hosts: &[Host]) {
+        self.sweeping = false;
+
+        match found {
+            Ok(found) => {
+                let nfound = found.len();
+
+                self.candidates = found
+                    .into_iter()
+                    .filter(|host| {
+                        !hosts
+                            .iter()
+                            .any(|present| present.host.eq_ignore_ascii_case(&host.host))
+                    })
+                    .map(|host| (host, true))
+                    .collect();
+                self.status = format!("{} found, {} new", nfound, self.candidates.len());
+            }
+            Err(e) => {
+                self.candidates = Vec::new();
+                self.status = e;
+            }
+        }
+    }
+
+    // the checked candidates, in the group as it is now
+    pub fn accepted(&self) -> Vec<Host> {
+        self.candidates
+            .iter()
+            .filter(|(_, accept)| *accept)
+            .map(|(host, _)| Host {
+                group: self.group.clone(),
+                ..host.clone()
+            })
+            .collect()
+    }
+
+    pub fn view(&self) -> Element<'_, Message, Renderer> {
+        let controls = row![
+            text_input("192.168.1.0/24", &self.cidr, Message::DiscoverCidr)
+                .size(16)
+                .width(160),
+            text_input("group", &self.group, Message::DiscoverGroup)
+                .size(16)
+                .width(120),
+            match self.sweeping {
+                true => iced::widget::button(text("sweep".to_string()).size(13)),
+                false => iced::widget::button(text("sweep".to_string()).size(13))
+                    .on_press(Message::DiscoverSweep),
+            }
+            .height(28)
+            .style(theme::Button::Primary),
+            iced::widget::button(text("accept".to_string()).size(13))
+                .height(28)
+                .style(theme::Button::Primary)
+                .on_press(Message::DiscoverAccept),
+            iced::widget::button(text("cancel".to_string()).size(13))
+                .height(28)
+                .style(theme::Button::Secondary)
+                .on_press(Message::DiscoverCancel),
+        ]
+        .spacing(8)
+        .align_items(Alignment::Center);
+
+        let mut candidates = Column::new().align_items(Alignment::Start).spacing(4);
+        for (id, (host, accept)) in self.candidates.iter().enumerate() {
+            candidates = candidates.push(checkbox(
+                format!("{} ({})", host.label, host.host),
+                *accept,
+                move |accept| Message::DiscoverToggle(id, accept),
+            ));
+        }
+
+        Column::new()
+            .spacing(8)
+            .push(text("discover hosts").size(16))
+            .push(controls)
+            .push(text(&self.status).size(14))
+            .push(Scrollable::new(candidates).height(Length::Fill))
+            .into()
+    }
+}
+
+// silence the host shown in the info box for some minutes
+#[derive(Debug, Default)]
+pub struct SilenceBox {
+    pub host: Option<usize>,
+    pub minutes: String,
+}
+
+impl SilenceBox {
+    pub const MINUTES: i64 = 60;
+
+    pub fn new() -> Self {
+        SilenceBox {
+            host: None,
+            minutes: Self::MINUTES.to_string(),
+        }
+    }
+
+    pub fn minutes(&self) -> Option<i64> {
+        self.minutes
+            .trim()
+            .parse()
+            .ok()
+            .filter(|minutes| *minutes > 0)
+    }
+
+    pub fn view(&self, silenced: bool) -> Element<'_, Message, Renderer> {
+        let controls = row![
+            text_input("minutes", &self.minutes, Message::SilenceMinutes)
+                .size(13)
+                .width(48),
+            iced::widget::button(text("silence".to_string()).size(13))
+                .style(theme::Button::Primary)
+                .on_press(Message::Silence),
+        ]
+        .spacing(4)
+        .align_items(Alignment::Center);
+
+        match silenced {
+            true => controls
+                .push(
+                    iced::widget::button(text("unsilence".to_string()).size(13))
+                        .style(theme::Button::Secondary)
+                        .on_press(Message::Unsilence),
+                )
+                .into(),
+            false => controls.into(),
+        }
+    }
+}
+
+// a traceroute of the host in the info box, hop by hop
+#[derive(Debug)]
+pub struct TraceBox {
+    pub trace: Trace,
+}
+
+impl TraceBox {
+    pub fn new(host: &Host, poll: &Poll, settings: &settings::Traceroute) -> Self {
+        TraceBox {
+            trace: Trace::new(&host.host, poll.dns.resolve(&host.host), settings),
+        }
+    }
+
+    pub fn view(&self) -> Element<'_, Message, Renderer> {
+        let mut hops = Column::new().align_items(Alignment::Start).spacing(2);
+        for line in self.trace.lines.iter().skip(1) {
+            hops = hops.push(text(line).size(13));
+        }
+
+        Column::new()
+            .spacing(8)
+            .push(text(&self.trace.lines[0]).size(16))
+            .push(Scrollable::new(hops).height(Length::Fill))
+            .push(
+                iced::widget::button(text("close".to_string()).size(13))
+                    .height(28)
+                    .style(theme::Button::Primary)
+                    .on_press(Message::TraceClose),
+            )
+            .into()
+    }
+}
+
+// host file problems found on load
+#[derive(Debug, Default)]
+pub struct ValidationBox {
+    summary: String,
+    lines: Vec<String>,
+}
+
+impl ValidationBox {
+    pub fn new(validations: &[Validation]) -> Self {
+        let count = |severity| {
+            validations
+                .iter()
+                .map(|validation| validation.count(severity))
+                .sum::<usize>()
+        };
+
+        ValidationBox {
+            summary: format!(
+                "{} host files: {} errors, {} warnings",
+                validations.len(),
+                count(Severity::Error),
+                count(Severity::Warning)
+            ),
+            lines: validations
+                .iter()
+                .flat_map(|validation| validation.lines())
+                .collect(),
+        }
+    }
+
+    pub fn view(&self) -> Element<'_, Message, Renderer> {
+        let mut lines = Column::new().align_items(Alignment::Start).spacing(2);
+        for line in &self.lines {
+            lines = lines.push(text(line).size(13));
+        }
+
+        Column::new()
+            .spacing(8)
+            .push(text(&self.summary).size(16))
+            .push(Scrollable::new(lines).height(Length::Fill))
+            .push(
+                iced::widget::button(text("dismiss".to_string()).size(13))
+                    .height(28)
+                    .style(theme::Button::Primary)
+                    .on_press(Message::ValidationDismiss),
+            )
+            .into()
+    }
+}
+
 // main frame
 pub struct SnitchUi {
+    alerts: Alerts,
+    cursor: Option<usize>,
+    discover: Option<DiscoverBox>,
     filter: RwLock<String>,
     group_box: GroupBox,
-    groups: Vec<String>,
+    groups: GroupTree,
+    history: History,
     host_box: HostBox,
     hosts: Option<Vec<Host>>,
+    hosts_path: PathBuf,
     info_box: InfoBox,
+    keys: Keys,
     last: Vec<Event>,
-    poll: Poll,
-    states: RwLock<Vec<bool>>,
+    last_poll: Option<time::OffsetDateTime>,
+    now: time::OffsetDateTime,
+    poll: Arc<Poll>,
+    polling: bool,
+    search: String,
+    silence_box: SilenceBox,
+    states: RwLock<Vec<State>>,
     status_bar: StatusBar,
+    sweeper: Arc<Mutex<Poll>>,
+    theme: Theme,
+    trace: Option<TraceBox>,
+    traceroute: settings::Traceroute,
     poll_interval_secs: u64,
+    validation: Option<ValidationBox>,
 }
 
 #[derive(Clone, Debug)]
 pub enum Message {
     Clear,
     ClockTick(time::OffsetDateTime),
+    Discover,
+    DiscoverAccept,
+    DiscoverCancel,
+    DiscoverCidr(String),
+    DiscoverFound(Result<Vec<Host>, String>),
+    DiscoverGroup(String),
+    DiscoverSweep,
+    DiscoverToggle(usize, bool),
     EventOccurred(Event),
     GroupPress(usize),
+    GroupToggle(usize),
     HostPress(usize),
     Poll,
+    // None when the poll's thread died
+    Polled(Option<Vec<State>>),
+    Search(String),
+    Silence,
+    SilenceMinutes(String),
+    Trace,
+    TraceClose,
+    Unsilence,
+    ValidationDismiss,
 }
 
 impl SnitchUi {
     const POLL_INTERVAL: u64 = 180; // sntop uses 180 seconds by default
     const HEADER_TEXT_SIZE: u16 = 20;
     const FRAME_PADDING: u16 = 5;
+
+    fn now() -> time::OffsetDateTime {
+        time::OffsetDateTime::now_local().unwrap_or_else(|_| time::OffsetDateTime::now_utc())
+    }
+
+    // poll in a thread, so ping bursts and slow probes don't hold up
+    // the window, and deliver the states. a poll already under way
+    // isn't started again
+    fn poll_hosts(&mut self) -> Command<Message> {
+        let hosts = match &self.hosts {
+            Some(hosts) if !self.polling => hosts.clone(),
+            _ => return Command::none(),
+        };
+        let poll = self.poll.clone();
+        let (polled, receiver) = oneshot::channel();
+
+        self.polling = true;
+        std::thread::spawn(move || {
+            polled.send(poll.poll_all(&hosts)).ok();
+        });
+
+        Command::perform(async move { receiver.await.ok() }, Message::Polled)
+    }
+
+    // hosts added while they were polled are polled again
+    fn polled(&mut self, polled: Option<Vec<State>>) -> Command<Message> {
+        self.polling = false;
+
+        let (hosts, polled) = match (&self.hosts, polled) {
+            (Some(hosts), Some(polled)) if polled.len() == hosts.len() => (hosts, polled),
+            (Some(_), Some(_)) => return self.poll_hosts(),
+            _ => {
+                self.last_poll = Some(Self::now());
+                return Command::none();
+            }
+        };
+        let mut states = self.states.write().unwrap();
+
+        self.history.record(hosts, &states, &polled).ok();
+        self.alerts
+            .fire(hosts, &states, &polled, &self.poll.quiet(hosts, &polled));
+        *states = polled;
+        self.last_poll = Some(Self::now());
+
+        Command::none()
+    }
+
+    // add hosts to the primary hosts file, and poll
+    fn add_hosts(&mut self, added: Vec<Host>) -> Command<Message> {
+        self.info_box.clear();
+        let mut hosts = match self.hosts.take() {
+            Some(hosts) => hosts,
+            None => {
+                self.info_box
+                    .write("not adding hosts: the host files didn't load".to_string());
+                return Command::none();
+            }
+        };
+        let added = added
+            .into_iter()
+            .map(|host| Host {
+                source: self.hosts_path.clone(),
+                ..host
+            })
+            .collect();
+        let present = hosts.len();
+        let nadded = Import::merge(&mut hosts, added);
+
+        match Host::write_back(&hosts[present..]) {
+            Ok(()) => self.info_box.write(format!("added {} hosts", nadded)),
+            Err(e) => self.info_box.write(e),
+        }
+
+        self.groups = GroupTree::new(&hosts);
+        self.hosts = Some(hosts);
+        self.poll_hosts()
+    }
+
+    fn show_host(&mut self, id: usize) {
+        let host = &self.hosts.as_ref().unwrap()[id];
+        let state = self
+            .states
+            .read()
+            .unwrap()
+            .get(id)
+            .copied()
+            .unwrap_or_default();
+
+        self.silence_box.host = Some(id);
+        self.info_box.clear();
+        for (n, line) in Host::details(&self.poll, host, state)
+            .into_iter()
+            .enumerate()
+        {
+            if n > 0 {
+                self.info_box.scroll();
+            }
+            self.info_box.write(line);
+        }
+    }
+
+    // silence the host in the info box, or lift its silence
+    fn silence(&mut self, minutes: i64) {
+        let host = match (self.silence_box.host, &self.hosts) {
+            (Some(id), Some(hosts)) => match hosts.get(id) {
+                Some(host) => host,
+                None => return,
+            },
+            _ => return,
+        };
+
+        match self.poll.schedule.silence(host, minutes) {
+            Ok(()) => {
+                if let Some(id) = self.silence_box.host {
+                    self.show_host(id)
+                }
+            }
+            Err(e) => self.info_box.write(format!("silences: {}", e)),
+        }
+    }
+
+    // traceroute the host in the info box
+    fn trace(&mut self) {
+        if let (Some(id), Some(hosts)) = (self.silence_box.host, &self.hosts) {
+            if let Some(host) = hosts.get(id) {
+                self.trace = Some(TraceBox::new(host, &self.poll, &self.traceroute))
+            }
+        }
+    }
+
+    fn visible(&self) -> Vec<usize> {
+        let filter = self.filter.read().unwrap();
+
+        match &self.hosts {
+            Some(hosts) => self.host_box.visible(&filter, &self.search, hosts),
+            None => Vec::new(),
+        }
+    }
+
+    // key names as used in the settings key bindings
+    fn key_name(key_code: KeyCode) -> String {
+        let name = format!("{:?}", key_code).to_lowercase();
+
+        match key_code {
+            KeyCode::Slash => "/".to_string(),
+            _ => match name.strip_prefix("key") {
+                Some(digit) if digit.len() == 1 => digit.to_string(),
+                _ => name,
+            },
+        }
+    }
+
+    fn key_press(&mut self, key_code: KeyCode) -> Command<Message> {
+        let key = Self::key_name(key_code);
+        let visible = self.visible();
+        let steps = [
+            (&self.keys.left, (-1, 0)),
+            (&self.keys.right, (1, 0)),
+            (&self.keys.up, (0, -1)),
+            (&self.keys.down, (0, 1)),
+        ];
+        let step = steps
+            .iter()
+            .find(|(name, _)| **name == key)
+            .map(|(_, step)| *step);
+
+        if let Some((dx, dy)) = step {
+            self.cursor = self.host_box.step(self.cursor, visible.len(), dx, dy)
+        } else if key == self.keys.details {
+            if let Some(id) = self.cursor.and_then(|cursor| visible.get(cursor)) {
+                self.show_host(*id)
+            }
+        } else if key == self.keys.refresh {
+            return self.poll_hosts();
+        } else if key == self.keys.silence {
+            if let Some(id) = self.cursor.and_then(|cursor| visible.get(cursor)) {
+                let silenced = match &self.hosts {
+                    Some(hosts) => self.poll.schedule.silenced_until(&hosts[*id]).is_some(),
+                    None => false,
+                };
+
+                self.silence_box.host = Some(*id);
+                self.silence(match silenced {
+                    true => 0,
+                    false => self.silence_box.minutes().unwrap_or(SilenceBox::MINUTES),
+                })
+            }
+        } else if key == self.keys.trace {
+            if let Some(id) = self.cursor.and_then(|cursor| visible.get(cursor)) {
+                self.show_host(*id);
+                self.trace()
+            }
+        } else if key == self.keys.search {
+            return text_input::focus(SEARCH_ID.clone());
+        } else if key == self.keys.clear {
+            return self.update(Message::Clear);
+        } else if let Some(n) = self.keys.groups.iter().position(|group| group == &key) {
+            if let Some(id) = self.groups.visible().get(n) {
+                return self.update(Message::GroupPress(*id));
+            }
+        }
+
+        Command::none()
+    }
+
+    // there's no window to show it in yet, so a pinger that can't
+    // be opened ends the program like the terminal ui's does
+    fn open_poll(env: &Environment) -> Poll {
+        Poll::new(env).unwrap_or_else(|e| {
+            eprintln!("rsnitch-rs: {}", e);
+            std::process::exit(1)
+        })
+    }
+
+    // seconds until the next scheduled poll
+    fn next_poll_secs(&self) -> u64 {
+        match &self.last_poll {
+            Some(last_poll) => {
+                let elapsed = (self.now - *last_poll).whole_seconds().max(0) as u64;
+
+                self.poll_interval_secs.saturating_sub(elapsed)
+            }
+            None => 0,
+        }
+    }
 }
 
 impl Application for SnitchUi {
@@ -337,102 +981,196 @@ impl Application for SnitchUi {
     type Message = Message;
 
     fn new(env: Environment) -> (SnitchUi, Command<Message>) {
-        let hosts = Host::load(&env);
-        let mut groups: Vec<String> = Vec::new();
-
-        match &hosts {
-            Some(hosts) => {
-                for host in hosts {
-                    match groups.iter().find(|group| group == &&host.group) {
-                        Some(_) => (),
-                        None => groups.push(host.group.clone()),
-                    }
-                }
-            }
-            None => (),
-        }
+        // hosts that didn't load are None, and the reason is shown
+        let (hosts, load_error) = match Host::load(&env) {
+            Ok(hosts) => (Some(hosts), None),
+            Err(e) => (None, Some(e)),
+        };
+        let groups = match &hosts {
+            Some(hosts) => GroupTree::new(hosts),
+            None => GroupTree::default(),
+        };
 
-        let filter = RwLock::new(String::new());
-        let group_box = GroupBox::new(&env, 5);
+        let cursor = None;
+        let discover = None;
+        let filter = RwLock::new(env.group.clone().unwrap_or_default());
+        let group_box = GroupBox::new(&env, 12);
         let host_box = HostBox::new(&env, 5);
+        let hosts_path = Host::path(&env);
+        // looking up every host name would hold up the first frame,
+        // so that's left to rsnitch-rs validate
+        let validations: Vec<Validation> = Host::files(&env)
+            .iter()
+            .map(|path| Validation::validate(path, false))
+            .collect();
+        let validation = if validations
+            .iter()
+            .all(|validation| validation.diagnostics.is_empty())
+        {
+            None
+        } else {
+            Some(ValidationBox::new(&validations))
+        };
         let info_box = InfoBox::new(&env, 6, 40);
+        if let Some(e) = load_error {
+            info_box.write(e);
+        }
+        let keys = match &env.settings {
+            Some(settings) => settings.keys.clone(),
+            None => Keys::default(),
+        };
         let last = Vec::<Event>::new();
-        let poll = Poll::new(&env);
+        let poll = Arc::new(Self::open_poll(&env));
 
-        let states = RwLock::new(match &hosts {
-            Some(hosts) => poll.poll_all(hosts),
-            None => Vec::new(),
-        });
+        // unknown until the first poll, which is started with the window
+        let now = Self::now();
+        let states = RwLock::new(vec![
+            State::Unknown;
+            hosts.as_ref().map_or(0, |hosts| hosts.len())
+        ]);
 
         let status_bar = StatusBar::new(&env);
+        let theme = match &env.settings {
+            Some(settings) => state::theme(&settings.theme),
+            None => Theme::Light,
+        };
 
-        let snitch_ui = SnitchUi {
+        let mut snitch_ui = SnitchUi {
+            alerts: Alerts::new(&env),
+            cursor,
+            discover,
             filter,
             group_box,
             groups,
+            history: History::new(&env),
             host_box,
             hosts,
+            hosts_path,
             info_box,
+            keys,
             last,
+            last_poll: None,
+            now,
             poll,
-            poll_interval_secs: Self::POLL_INTERVAL,
+            polling: false,
+            poll_interval_secs: env.interval.unwrap_or(Self::POLL_INTERVAL),
+            search: String::new(),
+            silence_box: SilenceBox::new(),
             states,
             status_bar,
+            sweeper: Arc::new(Mutex::new(Self::open_poll(&env))),
+            theme,
+            trace: None,
+            traceroute: match &env.settings {
+                Some(settings) => settings.traceroute.clone(),
+                None => settings::Traceroute::default(),
+            },
+            validation,
         };
 
-        (snitch_ui, Command::none())
+        let poll = snitch_ui.poll_hosts();
+
+        (snitch_ui, poll)
     }
 
     fn title(&self) -> String {
-        String::from("rsnitch 0.0.2")
+        let states = self.states.read().unwrap();
+
+        match State::count(&states, State::Down) {
+            0 => String::from("rsnitch 0.0.2"),
+            down => format!("rsnitch 0.0.2 - {} down", down),
+        }
+    }
+
+    fn theme(&self) -> Theme {
+        self.theme.clone()
     }
 
     fn update(&mut self, message: Self::Message) -> Command<Message> {
         match message {
-            Message::Poll => match &self.hosts {
-                Some(hosts) => {
-                    let mut states = self.states.write().unwrap();
-
-                    *states = self.poll.poll_all(hosts);
-                }
-                None => (),
-            },
+            Message::Poll => return self.poll_hosts(),
+            Message::Polled(polled) => return self.polled(polled),
             Message::GroupPress(id) => {
                 let mut filter = self.filter.write().unwrap();
 
-                *filter = self.groups[id].clone();
+                *filter = self.groups.group(id).path.clone();
+                self.cursor = None;
             }
+            Message::GroupToggle(id) => self.groups.toggle(id),
             Message::Clear => {
                 let mut filter = self.filter.write().unwrap();
 
                 *filter = String::new();
+                self.search = String::new();
+                self.cursor = None;
             }
-            Message::HostPress(id) => {
-                let host = &self.hosts.as_ref().unwrap()[id];
-
-                self.info_box.clear();
-                self.info_box.write(format!("host: {}", host.host));
-                self.info_box.scroll();
-                self.info_box.write(format!("group: {}", host.group));
-                self.info_box.scroll();
-                self.info_box.write(format!("label: {}", host.label));
-                self.info_box.scroll();
-                self.info_box
-                    .write(format!("info: {}", Host::info(&self.poll, host)));
+            Message::Search(search) => {
+                self.search = search;
+                self.cursor = None;
             }
-            Message::ClockTick(_t) => match &self.hosts {
-                Some(hosts) => {
-                    let mut states = self.states.write().unwrap();
-
-                    *states = self.poll.poll_all(hosts);
-                }
-                None => (),
+            Message::HostPress(id) => self.show_host(id),
+            Message::SilenceMinutes(minutes) => self.silence_box.minutes = minutes,
+            Message::Silence => match self.silence_box.minutes() {
+                Some(minutes) => self.silence(minutes),
+                None => self.info_box.write(format!(
+                    "{}: not a number of minutes",
+                    self.silence_box.minutes
+                )),
             },
+            Message::Unsilence => self.silence(0),
+            Message::Trace => self.trace(),
+            Message::TraceClose => self.trace = None,
+            Message::Discover => self.discover = Some(DiscoverBox::new()),
+            Message::DiscoverCancel => self.discover = None,
+            Message::ValidationDismiss => self.validation = None,
+            Message::DiscoverCidr(cidr) => {
+                if let Some(discover) = &mut self.discover {
+                    discover.cidr = cidr
+                }
+            }
+            Message::DiscoverGroup(group) => {
+                if let Some(discover) = &mut self.discover {
+                    discover.group = group
+                }
+            }
+            Message::DiscoverToggle(id, accept) => {
+                if let Some(discover) = &mut self.discover {
+                    discover.candidates[id].1 = accept
+                }
+            }
+            Message::DiscoverSweep => {
+                if let Some(discover) = &mut self.discover {
+                    return discover.sweep(&self.sweeper);
+                }
+            }
+            Message::DiscoverFound(found) => {
+                if let Some(discover) = &mut self.discover {
+                    discover.found(found, self.hosts.as_deref().unwrap_or_default())
+                }
+            }
+            Message::DiscoverAccept => {
+                if let Some(discover) = self.discover.take() {
+                    return self.add_hosts(discover.accepted());
+                }
+            }
+            Message::ClockTick(now) => {
+                self.now = now;
+                if let Some(trace) = &mut self.trace {
+                    trace.trace.drain()
+                }
+                if self.next_poll_secs() == 0 {
+                    return self.poll_hosts();
+                }
+            }
             Message::EventOccurred(event)
                 if event == Event::Window(window::Event::CloseRequested) =>
             {
                 let _ = window::close::<Message>();
             }
+            Message::EventOccurred(Event::Keyboard(keyboard::Event::KeyPressed {
+                key_code,
+                modifiers: _,
+            })) => return self.key_press(key_code),
             Message::EventOccurred(_) => (),
         }
 
@@ -440,47 +1178,80 @@ impl Application for SnitchUi {
     }
 
     fn subscription(&self) -> Subscription<Message> {
-        iced::time::every(std::time::Duration::from_millis(
-            self.poll_interval_secs * 1000,
-        ))
-        .map(|_| {
-            Message::ClockTick(
-                time::OffsetDateTime::now_local()
-                    .unwrap_or_else(|_| time::OffsetDateTime::now_utc()),
-            )
-        })
+        Subscription::batch(vec![
+            iced::time::every(std::time::Duration::from_millis(1000))
+                .map(|_| Message::ClockTick(Self::now())),
+            // events a focused widget, like the search box, consumed aren't shortcuts
+            subscription::events_with(|event, status| match status {
+                event::Status::Ignored => Some(Message::EventOccurred(event)),
+                event::Status::Captured => None,
+            }),
+        ])
     }
 
     fn view(&self) -> Element<'_, Message, Renderer> {
-        let hosts = self.hosts.as_ref().unwrap();
+        let title = self.title();
+        let visible = self.visible();
+        let hosts = self.hosts.as_deref().unwrap_or_default();
         let states = self.states.read().unwrap();
         let filter = self.filter.read().unwrap();
+        let dns = self.poll.dns_failing(hosts);
+        let flapping = self.poll.flap.flapping(hosts);
+        let notes = self.poll.notes(hosts, &states);
 
         let button_col = Column::new()
             .align_items(Alignment::Start)
             .push(
-                Column::new()
-                    .push(self.group_box.view(&self.groups))
-                    .height(100),
-            )
-            .push(horizontal_rule(1))
-            .push(self.host_box.view(filter.to_string(), hosts, &states));
+                self.host_box
+                    .view(&visible, self.cursor, hosts, &states, &notes, &flapping),
+            );
 
         let info_col = Column::new()
             .align_items(Alignment::Start)
             .push(self.info_box.view());
 
+        let info_col = match self.silence_box.host.and_then(|id| hosts.get(id)) {
+            Some(host) => info_col
+                .push(
+                    self.silence_box
+                        .view(self.poll.schedule.silenced_until(host).is_some()),
+                )
+                .push(
+                    iced::widget::button(text("traceroute".to_string()).size(13))
+                        .style(theme::Button::Primary)
+                        .on_press(Message::Trace),
+                ),
+            None => info_col,
+        };
+
+        let info_col = info_col
+            .push(horizontal_rule(1))
+            .push(self.group_box.view(&self.groups, &states));
+
         let hosts_frame = Row::new()
             .align_items(Alignment::Start)
             .spacing(4)
-            .push(info_col.width(200))
-            .push(button_col);
+            .push(info_col.width(200));
+
+        let hosts_frame = match (&self.validation, &self.discover, &self.trace) {
+            (Some(validation), _, _) => hosts_frame.push(validation.view()),
+            (None, Some(discover), _) => hosts_frame.push(discover.view()),
+            (None, None, Some(trace)) => hosts_frame.push(trace.view()),
+            (None, None, None) => hosts_frame.push(button_col),
+        };
 
         let snitch = Column::new()
             .spacing(4)
-            .push(Text::new(self.title()).size(Self::HEADER_TEXT_SIZE))
-            .push(hosts_frame.height(340))
-            .push(self.status_bar.view(filter.to_string()));
+            .push(Text::new(title).size(Self::HEADER_TEXT_SIZE))
+            .push(hosts_frame.height(320))
+            .push(self.status_bar.view(
+                filter.to_string(),
+                &self.search,
+                &states,
+                dns.iter().filter(|failing| **failing).count(),
+                self.last_poll,
+                self.next_poll_secs(),
+            ));
 
         container(snitch)
             .width(Length::Fill)
@@ -1,2 +1,3 @@
 // pub mod button_builder;
 pub mod quad;
+pub mod state;
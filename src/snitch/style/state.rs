@@ -0,0 +1,158 @@
+// SPDX-FileCopyrightText: Copyright 2023 James M. Putnam (putnamjm.design@gmail.com)
+// SPDX-License-Identifier: MIT
+
+// host state colors, icons, and the application theme
+use {
+    super::super::host::State,
+    crate::settings,
+    iced::{
+        theme::{self, Palette},
+        widget::{button, text, Text},
+        Background, Color, Font, Theme, Vector,
+    },
+};
+
+pub const ICONS: Font = Font::External {
+    name: "Iced-Todos-Icons",
+    bytes: include_bytes!("../../../fonts/icons.ttf"),
+};
+
+// the icon font only has a check mark, so the other states
+// use plain characters that differ in shape as well as color
+pub fn icon(state: State) -> Text<'static> {
+    match state {
+        State::Up => text('\u{f00c}'.to_string()).font(ICONS),
+        State::Degraded => text("!"),
+        State::Down => text("x"),
+        State::Unreachable => text("-"),
+        State::Unknown => text("?"),
+    }
+}
+
+// "#rrggbb"
+pub fn color(hex: &str) -> Option<Color> {
+    let hex = hex.strip_prefix('#')?;
+
+    if hex.len() != 6 {
+        return None;
+    }
+
+    let channel = |n: usize| u8::from_str_radix(hex.get(n..n + 2)?, 16).ok();
+
+    Some(Color::from_rgb8(channel(0)?, channel(2)?, channel(4)?))
+}
+
+pub fn theme(settings: &settings::Theme) -> Theme {
+    match settings.preset.as_str() {
+        "dark" => Theme::Dark,
+        "custom" => match &settings.palette {
+            Some(palette) => {
+                let default = Palette::LIGHT;
+
+                Theme::custom(Palette {
+                    background: color(&palette.background).unwrap_or(default.background),
+                    text: color(&palette.text).unwrap_or(default.text),
+                    primary: color(&palette.primary).unwrap_or(default.primary),
+                    success: color(&palette.success).unwrap_or(default.success),
+                    danger: color(&palette.danger).unwrap_or(default.danger),
+                })
+            }
+            None => Theme::Light,
+        },
+        _ => Theme::Light,
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct StatePalette {
+    pub up: Color,
+    pub down: Color,
+    pub degraded: Color,
+    pub unreachable: Color,
+    pub unknown: Color,
+}
+
+impl Default for StatePalette {
+    fn default() -> Self {
+        Self::new(&settings::StateColors::default())
+    }
+}
+
+impl StatePalette {
+    pub fn new(colors: &settings::StateColors) -> Self {
+        let gray = Color::from_rgb8(0x99, 0x99, 0x99);
+
+        StatePalette {
+            up: color(&colors.up).unwrap_or(gray),
+            down: color(&colors.down).unwrap_or(gray),
+            degraded: color(&colors.degraded).unwrap_or(gray),
+            unreachable: color(&colors.unreachable).unwrap_or(gray),
+            unknown: color(&colors.unknown).unwrap_or(gray),
+        }
+    }
+
+    pub fn color(&self, state: State) -> Color {
+        match state {
+            State::Up => self.up,
+            State::Degraded => self.degraded,
+            State::Down => self.down,
+            State::Unreachable => self.unreachable,
+            State::Unknown => self.unknown,
+        }
+    }
+
+    pub fn button(&self, state: State) -> theme::Button {
+        theme::Button::Custom(Box::new(StateButton {
+            color: self.color(state),
+            flapping: false,
+        }))
+    }
+
+    // outlined in the text color, the state is in doubt
+    pub fn flapping(&self, state: State) -> theme::Button {
+        theme::Button::Custom(Box::new(StateButton {
+            color: self.color(state),
+            flapping: true,
+        }))
+    }
+}
+
+pub struct StateButton {
+    color: Color,
+    flapping: bool,
+}
+
+impl StateButton {
+    // black or white, whichever reads better on the button color
+    fn text_color(&self) -> Color {
+        let luma = 0.299 * self.color.r + 0.587 * self.color.g + 0.114 * self.color.b;
+
+        if luma > 0.5 {
+            Color::BLACK
+        } else {
+            Color::WHITE
+        }
+    }
+}
+
+impl button::StyleSheet for StateButton {
+    type Style = Theme;
+
+    fn active(&self, _style: &Self::Style) -> button::Appearance {
+        button::Appearance {
+            background: Some(Background::Color(self.color)),
+            border_radius: 2.0,
+            border_width: if self.flapping { 3.0 } else { 0.0 },
+            border_color: self.text_color(),
+            text_color: self.text_color(),
+            ..Default::default()
+        }
+    }
+
+    fn hovered(&self, style: &Self::Style) -> button::Appearance {
+        button::Appearance {
+            shadow_offset: Vector::new(0.0, 1.0),
+            ..self.active(style)
+        }
+    }
+}
@@ -0,0 +1,130 @@
+//  SPDX-FileCopyrightText: Copyright 2023 James M. Putnam (putnamjm.design@gmail.com)
+//  SPDX-License-Identifier: MIT
+
+// tls certificates
+//
+// handshake with a server and read its certificate. the point is
+// to see when the certificate expires, so invalid and self-signed
+// certificates are accepted rather than failing the handshake
+use {
+    native_tls::TlsConnector,
+    std::{
+        net::{IpAddr, TcpStream, ToSocketAddrs},
+        time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+    },
+    x509_parser::{extensions::GeneralName, parse_x509_certificate},
+};
+
+#[derive(Debug, Clone, Default)]
+pub struct Certificate {
+    pub subject: String,
+    pub issuer: String,
+    pub sans: Vec<String>,
+    pub not_after: String,
+    // unix seconds
+    pub expires: i64,
+}
+
+impl Certificate {
+    // negative once it has expired
+    pub fn days_left(&self) -> i64 {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|now| now.as_secs() as i64)
+            .unwrap_or(0);
+
+        (self.expires - now).div_euclid(24 * 60 * 60)
+    }
+
+    // lines for the host detail panel
+    pub fn lines(&self) -> Vec<String> {
+        vec![
+            format!("  subject: {}", self.subject),
+            format!("  issuer: {}", self.issuer),
+            format!("  sans: {}", self.sans.join(" ")),
+            format!("  not after: {}", self.not_after),
+        ]
+    }
+}
+
+pub struct Tls {}
+
+impl Tls {
+    pub const TIMEOUT: Duration = Duration::from_secs(3);
+
+    // the server's certificate and how long the handshake took. sni
+    // defaults to the host name, and isn't sent for an address
+    pub fn certificate(
+        host: &str,
+        port: u16,
+        sni: Option<&str>,
+    ) -> Result<(Certificate, Duration), String> {
+        let addr = match (host, port).to_socket_addrs() {
+            Ok(mut addrs) => match addrs.next() {
+                Some(addr) => addr,
+                None => return Err(format!("{}: no address", host)),
+            },
+            Err(e) => return Err(e.to_string()),
+        };
+
+        let domain = sni.unwrap_or(host);
+
+        let connector = TlsConnector::builder()
+            .danger_accept_invalid_certs(true)
+            .danger_accept_invalid_hostnames(true)
+            .use_sni(domain.parse::<IpAddr>().is_err())
+            .build()
+            .map_err(|e| e.to_string())?;
+
+        let start = Instant::now();
+        let stream = TcpStream::connect_timeout(&addr, Self::TIMEOUT).map_err(|e| e.to_string())?;
+        stream
+            .set_read_timeout(Some(Self::TIMEOUT))
+            .and_then(|_| stream.set_write_timeout(Some(Self::TIMEOUT)))
+            .map_err(|e| e.to_string())?;
+
+        let tls = connector
+            .connect(domain, stream)
+            .map_err(|e| e.to_string())?;
+        let elapsed = start.elapsed();
+
+        let der = match tls.peer_certificate() {
+            Ok(Some(cert)) => cert.to_der().map_err(|e| e.to_string())?,
+            Ok(None) => return Err("no certificate".to_string()),
+            Err(e) => return Err(e.to_string()),
+        };
+
+        let (_, cert) = parse_x509_certificate(&der).map_err(|e| e.to_string())?;
+
+        let sans = match cert.subject_alternative_name() {
+            Ok(Some(san)) => san
+                .value
+                .general_names
+                .iter()
+                .filter_map(|name| match name {
+                    GeneralName::DNSName(name) => Some(name.to_string()),
+                    GeneralName::IPAddress(bytes) => match bytes.len() {
+                        4 => Some(IpAddr::from(<[u8; 4]>::try_from(*bytes).ok()?).to_string()),
+                        16 => Some(IpAddr::from(<[u8; 16]>::try_from(*bytes).ok()?).to_string()),
+                        _ => None,
+                    },
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        let not_after = &cert.validity().not_after;
+
+        Ok((
+            Certificate {
+                subject: cert.subject().to_string(),
+                issuer: cert.issuer().to_string(),
+                sans,
+                not_after: not_after.to_string(),
+                expires: not_after.timestamp(),
+            },
+            elapsed,
+        ))
+    }
+}
@@ -0,0 +1,326 @@
+//  SPDX-FileCopyrightText: Copyright 2023 James M. Putnam (putnamjm.design@gmail.com)
+//  SPDX-License-Identifier: MIT
+
+// traceroute
+//
+// probe a host with the ttl stepped up from 1, and report each
+// router that answers with its reverse dns name and round trip,
+// until the host itself answers or max_hops. the probes are icmp
+// echo requests, or udp datagrams to port 33434 and up, and the
+// answers are read from a raw icmp socket, which is why rsnitch
+// runs under sudo. the traceroute section of settings
+//
+//     "traceroute": { "method": "udp", "max_hops": 30 }
+//
+// hops are sent over a channel as they're found, so a ui can show
+// them as they come in. ipv4 only
+use {
+    crate::settings,
+    dns_lookup::lookup_addr,
+    socket2::{Domain, Protocol, SockAddr, Socket, Type},
+    std::{
+        fmt,
+        io::Read,
+        net::{IpAddr, Ipv4Addr, SocketAddr},
+        sync::mpsc::{channel, Receiver, Sender, TryRecvError},
+        time::{Duration, Instant},
+    },
+};
+
+#[derive(Debug, Clone, Default)]
+pub struct Hop {
+    pub ttl: u8,
+    // no answer when None
+    pub addr: Option<Ipv4Addr>,
+    pub name: Option<String>,
+    pub rtt: Option<Duration>,
+}
+
+impl fmt::Display for Hop {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:>2}  ", self.ttl)?;
+
+        match (&self.addr, &self.name) {
+            (None, _) => return write!(f, "*"),
+            (Some(addr), Some(name)) => write!(f, "{} ({})", name, addr)?,
+            (Some(addr), None) => write!(f, "{}", addr)?,
+        }
+
+        match self.rtt {
+            Some(rtt) => write!(f, "  {:.1} ms", rtt.as_secs_f64() * 1000.0),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    Icmp,
+    Udp,
+}
+
+// a trace in progress, as the lines of a panel
+#[derive(Debug)]
+pub struct Trace {
+    pub lines: Vec<String>,
+    hops: Option<Receiver<Result<Hop, String>>>,
+}
+
+impl Trace {
+    pub fn new(name: &str, addr: Option<IpAddr>, settings: &settings::Traceroute) -> Self {
+        let start = match (addr, Traceroute::method(&settings.method)) {
+            (Some(addr), Ok(method)) => Ok((addr, method)),
+            (None, _) => Err(format!("{}: no address", name)),
+            (_, Err(e)) => Err(e),
+        };
+
+        match start {
+            Ok((addr, method)) => Trace {
+                lines: vec![format!("traceroute {} ({})", name, addr)],
+                hops: Some(Traceroute::start(addr, method, settings.max_hops)),
+            },
+            Err(e) => Trace {
+                lines: vec![format!("traceroute {}", name), e],
+                hops: None,
+            },
+        }
+    }
+
+    pub fn running(&self) -> bool {
+        self.hops.is_some()
+    }
+
+    // the hops found since the last call
+    pub fn drain(&mut self) {
+        let hops = match &self.hops {
+            Some(hops) => hops,
+            None => return,
+        };
+
+        loop {
+            match hops.try_recv() {
+                Ok(Ok(hop)) => self.lines.push(hop.to_string()),
+                Ok(Err(e)) => self.lines.push(e),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.lines.push("done".to_string());
+                    self.hops = None;
+                    break;
+                }
+            }
+        }
+    }
+}
+
+pub struct Traceroute {}
+
+impl Traceroute {
+    const TIMEOUT: Duration = Duration::from_secs(1);
+    const PORT: u16 = 33434;
+    const ECHO_REPLY: u8 = 0;
+    const UNREACHABLE: u8 = 3;
+    const ECHO_REQUEST: u8 = 8;
+    const TIME_EXCEEDED: u8 = 11;
+
+    pub fn method(name: &str) -> Result<Method, String> {
+        match name {
+            "icmp" => Ok(Method::Icmp),
+            "udp" => Ok(Method::Udp),
+            _ => Err(format!("{}: expected icmp or udp", name)),
+        }
+    }
+
+    // trace in a thread. the channel closes when the trace is done,
+    // after an error if it failed
+    pub fn start(addr: IpAddr, method: Method, max_hops: u8) -> Receiver<Result<Hop, String>> {
+        let (hops, receiver) = channel();
+
+        std::thread::spawn(move || {
+            if let Err(e) = Self::run(addr, method, max_hops, &hops) {
+                hops.send(Err(e)).ok();
+            }
+        });
+
+        receiver
+    }
+
+    fn run(
+        addr: IpAddr,
+        method: Method,
+        max_hops: u8,
+        hops: &Sender<Result<Hop, String>>,
+    ) -> Result<(), String> {
+        let addr = match addr {
+            IpAddr::V4(addr) => addr,
+            IpAddr::V6(_) => return Err(format!("{}: ipv6 isn't supported", addr)),
+        };
+
+        let icmp = Socket::new(Domain::IPV4, Type::RAW, Some(Protocol::ICMPV4))
+            .map_err(|e| format!("raw socket: {}", e))?;
+        let udp = match method {
+            Method::Udp => Some(
+                Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))
+                    .map_err(|e| format!("udp socket: {}", e))?,
+            ),
+            Method::Icmp => None,
+        };
+        let id = std::process::id() as u16;
+
+        for ttl in 1..=max_hops {
+            let seq = ttl as u16;
+            let dest = SockAddr::from(SocketAddr::new(
+                IpAddr::V4(addr),
+                match method {
+                    Method::Icmp => 0,
+                    Method::Udp => Self::PORT + seq,
+                },
+            ));
+
+            let start = Instant::now();
+            match &udp {
+                Some(udp) => {
+                    udp.set_ttl(ttl as u32).map_err(|e| e.to_string())?;
+                    udp.send_to(&[0; 32], &dest).map_err(|e| e.to_string())?;
+                }
+                None => {
+                    icmp.set_ttl(ttl as u32).map_err(|e| e.to_string())?;
+                    icmp.send_to(&Self::echo_request(id, seq), &dest)
+                        .map_err(|e| e.to_string())?;
+                }
+            }
+
+            let (hop, reached) = match Self::answer(&icmp, method, id, seq, start)? {
+                Some((from, reached)) => (
+                    Hop {
+                        ttl,
+                        addr: Some(from),
+                        name: lookup_addr(&IpAddr::V4(from))
+                            .ok()
+                            .filter(|name| name != &from.to_string()),
+                        rtt: Some(start.elapsed()),
+                    },
+                    reached || from == addr,
+                ),
+                None => (
+                    Hop {
+                        ttl,
+                        ..Default::default()
+                    },
+                    false,
+                ),
+            };
+
+            if hops.send(Ok(hop)).is_err() || reached {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    // the address that answered a probe, and whether the trace is
+    // over, or None if nothing did in time
+    fn answer(
+        icmp: &Socket,
+        method: Method,
+        id: u16,
+        seq: u16,
+        start: Instant,
+    ) -> Result<Option<(Ipv4Addr, bool)>, String> {
+        let mut packet = [0; 1500];
+        let mut reader = icmp;
+
+        loop {
+            let left = Self::TIMEOUT.saturating_sub(start.elapsed());
+            if left.is_zero() {
+                return Ok(None);
+            }
+
+            icmp.set_read_timeout(Some(left))
+                .map_err(|e| e.to_string())?;
+
+            let len = match reader.read(&mut packet) {
+                Ok(len) => len,
+                Err(e)
+                    if e.kind() == std::io::ErrorKind::WouldBlock
+                        || e.kind() == std::io::ErrorKind::TimedOut =>
+                {
+                    return Ok(None)
+                }
+                Err(e) => return Err(e.to_string()),
+            };
+
+            if let Some(answer) = Self::parse(&packet[..len], method, id, seq) {
+                return Ok(Some(answer));
+            }
+        }
+    }
+
+    // raw icmp sockets read the ip header too. an error carries the
+    // ip header and first bytes of the probe it's about
+    fn parse(packet: &[u8], method: Method, id: u16, seq: u16) -> Option<(Ipv4Addr, bool)> {
+        let header = (*packet.first()? as usize & 0x0f) * 4;
+        let from = Ipv4Addr::new(
+            *packet.get(12)?,
+            *packet.get(13)?,
+            *packet.get(14)?,
+            *packet.get(15)?,
+        );
+        let icmp = packet.get(header..)?;
+        let word = |bytes: &[u8], at: usize| {
+            Some(u16::from_be_bytes([*bytes.get(at)?, *bytes.get(at + 1)?]))
+        };
+
+        match *icmp.first()? {
+            Self::ECHO_REPLY if method == Method::Icmp => {
+                (word(icmp, 4)? == id && word(icmp, 6)? == seq).then_some((from, true))
+            }
+            kind @ (Self::TIME_EXCEEDED | Self::UNREACHABLE) => {
+                let probe = icmp.get(8..)?;
+                let payload = probe.get((*probe.first()? as usize & 0x0f) * 4..)?;
+
+                let ours = match method {
+                    Method::Icmp => {
+                        *payload.first()? == Self::ECHO_REQUEST
+                            && word(payload, 4)? == id
+                            && word(payload, 6)? == seq
+                    }
+                    Method::Udp => word(payload, 2)? == Self::PORT + seq,
+                };
+
+                ours.then_some((from, kind == Self::UNREACHABLE))
+            }
+            _ => None,
+        }
+    }
+
+    fn echo_request(id: u16, seq: u16) -> Vec<u8> {
+        let mut packet = vec![Self::ECHO_REQUEST, 0, 0, 0];
+
+        packet.extend(id.to_be_bytes());
+        packet.extend(seq.to_be_bytes());
+        packet.extend([0; 32]);
+
+        let checksum = Self::checksum(&packet);
+        packet[2..4].copy_from_slice(&checksum.to_be_bytes());
+
+        packet
+    }
+
+    fn checksum(bytes: &[u8]) -> u16 {
+        let mut sum = bytes
+            .chunks(2)
+            .map(|pair| match pair {
+                [hi, lo] => u16::from_be_bytes([*hi, *lo]) as u32,
+                [hi] => (*hi as u32) << 8,
+                _ => 0,
+            })
+            .sum::<u32>();
+
+        while sum >> 16 != 0 {
+            sum = (sum & 0xffff) + (sum >> 16);
+        }
+
+        !(sum as u16)
+    }
+}
@@ -0,0 +1,921 @@
+//  SPDX-FileCopyrightText: Copyright 2023 James M. Putnam (putnamjm.design@gmail.com)
+//  SPDX-License-Identifier: MIT
+
+// hosts file validation
+//
+// check a hosts file and report every problem with its file,
+// line, and column. toml's Spanned has where each key and value
+// starts, but serde doesn't keep positions for json or yaml, so
+// those have small scanners that find where each table's keys and
+// each list's items start
+use {
+    super::{depend::Depend, import::Import, probe::Probe},
+    crate::{format::Format, interpolate::Interpolate},
+    dns_lookup::lookup_host,
+    serde::de::{Deserialize, Deserializer, MapAccess, SeqAccess, Visitor},
+    serde_json::Value,
+    std::{
+        fmt,
+        path::{Path, PathBuf},
+    },
+    toml::Spanned,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+#[derive(Debug, Default)]
+pub struct Validation {
+    pub path: PathBuf,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+// where a value starts, and the keys of a table or the items of a
+// list in it. a key's span starts where the key does
+#[derive(Debug, Default)]
+struct Span {
+    start: usize,
+    keys: Vec<(String, Span)>,
+    items: Vec<Span>,
+}
+
+impl Span {
+    fn get(&self, key: &str) -> Option<&Span> {
+        self.keys
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, span)| span)
+    }
+
+    // where a key starts, else where this does
+    fn key(&self, key: &str) -> usize {
+        self.get(key).map_or(self.start, |span| span.start)
+    }
+
+    // where the nth item starts, else where this does
+    fn item(&self, n: usize) -> usize {
+        self.items.get(n).map_or(self.start, |span| span.start)
+    }
+}
+
+// a toml value's spans, from toml's Spanned keys and values
+impl<'de> Deserialize<'de> for Span {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(SpanVisitor)
+    }
+}
+
+struct SpanVisitor;
+
+impl<'de> Visitor<'de> for SpanVisitor {
+    type Value = Span;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a toml value")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Span, A::Error> {
+        let mut span = Span::default();
+
+        while let Some(key) = map.next_key::<Spanned<String>>()? {
+            let start = key.span().start;
+            let value = map.next_value::<Spanned<Span>>()?.into_inner();
+
+            span.keys.push((key.into_inner(), Span { start, ..value }))
+        }
+
+        Ok(span)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Span, A::Error> {
+        let mut span = Span::default();
+
+        while let Some(item) = seq.next_element::<Spanned<Span>>()? {
+            let start = item.span().start;
+
+            span.items.push(Span {
+                start,
+                ..item.into_inner()
+            })
+        }
+
+        Ok(span)
+    }
+
+    fn visit_bool<E>(self, _: bool) -> Result<Span, E> {
+        Ok(Span::default())
+    }
+
+    fn visit_i64<E>(self, _: i64) -> Result<Span, E> {
+        Ok(Span::default())
+    }
+
+    fn visit_u64<E>(self, _: u64) -> Result<Span, E> {
+        Ok(Span::default())
+    }
+
+    fn visit_f64<E>(self, _: f64) -> Result<Span, E> {
+        Ok(Span::default())
+    }
+
+    fn visit_str<E>(self, _: &str) -> Result<Span, E> {
+        Ok(Span::default())
+    }
+}
+
+impl fmt::Display for Validation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for diagnostic in &self.diagnostics {
+            writeln!(f, "{}", self.line(diagnostic))?
+        }
+
+        write!(
+            f,
+            "{}: {} errors, {} warnings",
+            self.path.display(),
+            self.count(Severity::Error),
+            self.count(Severity::Warning)
+        )
+    }
+}
+
+impl Validation {
+    const FIELDS: [&str; 3] = ["group", "host", "label"];
+    const OPTIONAL_FIELDS: [&str; 4] = ["comment", "probes", "thresholds", "depends_on"];
+    const TABLE_FIELDS: [&str; 2] = ["hosts", "include"];
+
+    pub fn count(&self, severity: Severity) -> usize {
+        self.diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == severity)
+            .count()
+    }
+
+    // file:line:column: severity: message
+    pub fn line(&self, diagnostic: &Diagnostic) -> String {
+        format!(
+            "{}:{}:{}: {}: {}",
+            self.path.display(),
+            diagnostic.line,
+            diagnostic.column,
+            match diagnostic.severity {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+            },
+            diagnostic.message
+        )
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        self.diagnostics
+            .iter()
+            .map(|diagnostic| self.line(diagnostic))
+            .collect()
+    }
+
+    // resolve also looks up every host name, which can be slow
+    pub fn validate(path: &Path, resolve: bool) -> Validation {
+        let mut validation = Validation {
+            path: path.to_path_buf(),
+            diagnostics: Vec::new(),
+        };
+
+        if Import::from_path(path).is_some() {
+            validation.push(Severity::Warning, "", 0, "not a hosts file, not validated");
+            return validation;
+        }
+
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) => {
+                validation.push(Severity::Error, "", 0, &e.to_string());
+                return validation;
+            }
+        };
+
+        let format = Format::from_path(path).unwrap_or(Format::Json);
+
+        let value = match Self::parse(format, &text) {
+            Ok(value) => value,
+            Err((offset, message)) => {
+                validation.push(Severity::Error, &text, offset, &message);
+                return validation;
+            }
+        };
+
+        // a span the scanners miss points at the start of the file
+        let root = match format {
+            Format::Json => Self::flow_span(&text, 0).0,
+            Format::Toml => toml::from_str::<Span>(&text).unwrap_or_default(),
+            Format::Yaml => Self::yaml_span(&text),
+        };
+
+        let (entries, spans) = match &value {
+            Value::Array(entries) => (entries, &root.items),
+            Value::Object(table) => {
+                validation.check_table(path, &text, table, &root);
+
+                match (table.get("hosts"), root.get("hosts")) {
+                    (Some(Value::Array(entries)), Some(hosts)) => (entries, &hosts.items),
+                    (Some(Value::Array(entries)), None) => (entries, &root.items),
+                    _ => return validation,
+                }
+            }
+            _ => {
+                validation.push(
+                    Severity::Error,
+                    &text,
+                    0,
+                    "expected a list of hosts, or a table of hosts and includes",
+                );
+                return validation;
+            }
+        };
+
+        validation.check(&text, entries, spans, resolve);
+        validation
+    }
+
+    fn push(&mut self, severity: Severity, text: &str, offset: usize, message: &str) {
+        let (line, column) = Self::line_column(text, offset);
+
+        self.diagnostics.push(Diagnostic {
+            severity,
+            line,
+            column,
+            message: message.to_string(),
+        })
+    }
+
+    // 1-based, columns in characters
+    fn line_column(text: &str, offset: usize) -> (usize, usize) {
+        let before = &text[..offset.min(text.len())];
+        let line = before.matches('\n').count() + 1;
+        let column = match before.rfind('\n') {
+            Some(newline) => before[newline + 1..].chars().count() + 1,
+            None => before.chars().count() + 1,
+        };
+
+        (line, column)
+    }
+
+    // the top level list or table, or where parsing failed and why
+    fn parse(format: Format, text: &str) -> Result<Value, (usize, String)> {
+        let offset = |line: usize, column: usize| {
+            text.split_inclusive('\n')
+                .take(line.saturating_sub(1))
+                .map(|line| line.len())
+                .sum::<usize>()
+                + column.saturating_sub(1)
+        };
+
+        match format {
+            Format::Json => serde_json::from_str::<Value>(text)
+                .map_err(|e| (offset(e.line(), e.column()), e.to_string())),
+            Format::Yaml => serde_yaml::from_str::<Value>(text).map_err(|e| {
+                let at = match e.location() {
+                    Some(location) => offset(location.line(), location.column()),
+                    None => 0,
+                };
+
+                (at, e.to_string())
+            }),
+            Format::Toml => toml::from_str::<Value>(text).map_err(|e| {
+                let at = match e.span() {
+                    Some(span) => span.start,
+                    None => 0,
+                };
+
+                (at, e.message().to_string())
+            }),
+        }
+    }
+
+    // the table form's keys, and that every include is there
+    fn check_table(
+        &mut self,
+        path: &Path,
+        text: &str,
+        table: &serde_json::Map<String, Value>,
+        span: &Span,
+    ) {
+        let at = |key: &str| span.key(key);
+
+        for key in table.keys() {
+            if !Self::TABLE_FIELDS.contains(&key.as_str()) {
+                self.push(
+                    Severity::Warning,
+                    text,
+                    at(key),
+                    &format!("unknown field `{}`", key),
+                )
+            }
+        }
+
+        match table.get("hosts") {
+            None if !table.contains_key("include") => {
+                self.push(Severity::Error, text, 0, "no hosts and no includes")
+            }
+            None | Some(Value::Array(_)) => (),
+            Some(_) => self.push(
+                Severity::Error,
+                text,
+                at("hosts"),
+                "`hosts` should be a list",
+            ),
+        }
+
+        let dir = path.parent().unwrap_or_else(|| Path::new("."));
+
+        match table.get("include") {
+            None => (),
+            Some(Value::Array(includes)) => {
+                let item = |n: usize| span.get("include").map_or(span.start, |span| span.item(n));
+
+                for (n, include) in includes.iter().enumerate() {
+                    match include {
+                        Value::String(include) if Path::join(dir, include).exists() => (),
+                        Value::String(include) => self.push(
+                            Severity::Error,
+                            text,
+                            item(n),
+                            &format!("include `{}` not found", include),
+                        ),
+                        _ => self.push(
+                            Severity::Error,
+                            text,
+                            item(n),
+                            "includes should be file or directory names",
+                        ),
+                    }
+                }
+            }
+            Some(_) => self.push(
+                Severity::Error,
+                text,
+                at("include"),
+                "`include` should be a list",
+            ),
+        }
+    }
+
+    fn check(&mut self, text: &str, entries: &[Value], spans: &[Span], resolve: bool) {
+        let default_span = Span::default();
+        let mut hosts: Vec<(String, usize)> = Vec::new();
+        let mut labels: Vec<(String, usize)> = Vec::new();
+        let mut deps: Vec<(String, Vec<String>)> = Vec::new();
+        let mut dep_offsets: Vec<usize> = Vec::new();
+
+        for (id, entry) in entries.iter().enumerate() {
+            let span = spans.get(id).unwrap_or(&default_span);
+
+            let fields = match entry {
+                Value::Object(fields) => fields,
+                _ => {
+                    self.push(
+                        Severity::Error,
+                        text,
+                        span.start,
+                        "host entry isn't a table",
+                    );
+                    continue;
+                }
+            };
+
+            for field in Self::FIELDS {
+                match fields.get(field) {
+                    None => self.push(
+                        Severity::Error,
+                        text,
+                        span.start,
+                        &format!("missing field `{}`", field),
+                    ),
+                    Some(Value::String(_)) => (),
+                    Some(_) => self.push(
+                        Severity::Error,
+                        text,
+                        span.key(field),
+                        &format!("`{}` should be a string", field),
+                    ),
+                }
+            }
+
+            for key in fields.keys() {
+                if !Self::FIELDS.contains(&key.as_str())
+                    && !Self::OPTIONAL_FIELDS.contains(&key.as_str())
+                {
+                    self.push(
+                        Severity::Warning,
+                        text,
+                        span.key(key),
+                        &format!("unknown field `{}`", key),
+                    )
+                }
+            }
+
+            if let Some(Value::String(group)) = fields.get("group") {
+                if group.is_empty() || group.split('/').any(|name| name.is_empty()) {
+                    self.push(Severity::Error, text, span.key("group"), "empty group")
+                }
+            }
+
+            if let Some(Value::String(host)) = fields.get("host") {
+                match hosts
+                    .iter()
+                    .find(|(name, _)| name.eq_ignore_ascii_case(host))
+                {
+                    Some((_, first)) => {
+                        let (line, _) = Self::line_column(text, *first);
+
+                        self.push(
+                            Severity::Error,
+                            text,
+                            span.key("host"),
+                            &format!("duplicate host `{}`, first on line {}", host, line),
+                        )
+                    }
+                    None => hosts.push((host.clone(), span.key("host"))),
+                }
+
+                match Interpolate::expand(host) {
+                    Ok(expanded) if resolve && lookup_host(&expanded).is_err() => self.push(
+                        Severity::Warning,
+                        text,
+                        span.key("host"),
+                        &format!("`{}` doesn't resolve", host),
+                    ),
+                    Ok(_) => (),
+                    Err(e) => self.push(Severity::Error, text, span.key("host"), &e),
+                }
+            }
+
+            if let Some(Value::String(label)) = fields.get("label") {
+                match labels.iter().find(|(name, _)| name == label) {
+                    Some((_, first)) => {
+                        let (line, _) = Self::line_column(text, *first);
+
+                        self.push(
+                            Severity::Error,
+                            text,
+                            span.key("label"),
+                            &format!("duplicate label `{}`, first on line {}", label, line),
+                        )
+                    }
+                    None => labels.push((label.clone(), span.key("label"))),
+                }
+            }
+
+            if let Some(probes) = fields.get("probes") {
+                self.check_probes(text, probes, span.get("probes").unwrap_or(span))
+            }
+
+            match fields.get("depends_on") {
+                None => (),
+                Some(Value::Array(parents)) if parents.iter().all(Value::is_string) => {
+                    if let Some(Value::String(host)) = fields.get("host") {
+                        deps.push((
+                            host.clone(),
+                            parents
+                                .iter()
+                                .filter_map(|parent| parent.as_str().map(str::to_string))
+                                .collect(),
+                        ));
+                        dep_offsets.push(span.key("depends_on"))
+                    }
+                }
+                Some(_) => self.push(
+                    Severity::Error,
+                    text,
+                    span.key("depends_on"),
+                    "`depends_on` should be a list of host names",
+                ),
+            }
+        }
+
+        // only the hosts in this file, a cycle through other files
+        // is caught when the hosts are loaded
+        if let Some(cycle) = Depend::cycle(&deps) {
+            let offset = deps
+                .iter()
+                .position(|(name, _)| name == &cycle[0])
+                .map(|id| dep_offsets[id])
+                .unwrap_or(0);
+
+            self.push(
+                Severity::Error,
+                text,
+                offset,
+                &format!("dependency cycle {}", cycle.join(" -> ")),
+            )
+        }
+    }
+
+    fn check_probes(&mut self, text: &str, probes: &Value, span: &Span) {
+        let probes = match probes {
+            Value::Array(probes) => probes,
+            _ => {
+                self.push(
+                    Severity::Error,
+                    text,
+                    span.start,
+                    "`probes` should be a list",
+                );
+                return;
+            }
+        };
+
+        for (n, probe) in probes.iter().enumerate() {
+            let offset = span.item(n);
+
+            match probe.get("type") {
+                Some(Value::String(probe_type)) if !Probe::TYPES.contains(&probe_type.as_str()) => {
+                    self.push(
+                        Severity::Error,
+                        text,
+                        offset,
+                        &format!("unknown probe type `{}`", probe_type),
+                    );
+                }
+                Some(Value::String(_)) => {
+                    if let Err(e) = serde_json::from_value::<Probe>(probe.clone()) {
+                        self.push(Severity::Error, text, offset, &format!("probe: {}", e))
+                    }
+                }
+                _ => self.push(Severity::Error, text, offset, "probe without a `type`"),
+            }
+        }
+    }
+
+    // a json value, or a yaml flow collection, from offset, and
+    // where it ends. keys can be quoted or, in yaml, bare
+    fn flow_span(text: &str, offset: usize) -> (Span, usize) {
+        let bytes = text.as_bytes();
+        let skip = |mut at: usize| {
+            while bytes.get(at).is_some_and(u8::is_ascii_whitespace) {
+                at += 1
+            }
+            at
+        };
+
+        let mut at = skip(offset);
+        let mut span = Span {
+            start: at,
+            ..Default::default()
+        };
+
+        match bytes.get(at) {
+            Some(b'{') => {
+                at += 1;
+                loop {
+                    at = skip(at);
+                    match bytes.get(at) {
+                        None => break,
+                        Some(b'}') => {
+                            at += 1;
+                            break;
+                        }
+                        Some(b',') => at += 1,
+                        Some(_) => {
+                            let start = at;
+                            let (name, end) = Self::flow_scalar(text, at, b":,}");
+
+                            at = skip(end.max(start + 1));
+                            if bytes.get(at) == Some(&b':') {
+                                let (value, end) = Self::flow_span(text, at + 1);
+
+                                span.keys.push((name, Span { start, ..value }));
+                                at = end
+                            }
+                        }
+                    }
+                }
+            }
+            Some(b'[') => {
+                at += 1;
+                loop {
+                    at = skip(at);
+                    match bytes.get(at) {
+                        None => break,
+                        Some(b']') => {
+                            at += 1;
+                            break;
+                        }
+                        Some(b',') => at += 1,
+                        Some(_) => {
+                            let (item, end) = Self::flow_span(text, at);
+
+                            span.items.push(item);
+                            at = end
+                        }
+                    }
+                }
+            }
+            Some(_) => at = Self::flow_scalar(text, at, b",]}").1.max(at + 1),
+            None => (),
+        }
+
+        (span, at)
+    }
+
+    // a quoted or bare scalar, and where it ends. a bare one ends at
+    // any of the stop bytes
+    fn flow_scalar(text: &str, offset: usize, stops: &[u8]) -> (String, usize) {
+        match text.as_bytes()[offset] {
+            quote @ (b'"' | b'\'') => {
+                let mut scalar = String::new();
+                let mut chars = text[offset + 1..].char_indices();
+
+                while let Some((n, ch)) = chars.next() {
+                    match ch {
+                        '\\' if quote == b'"' => scalar.extend(chars.next().map(|(_, ch)| ch)),
+                        _ if ch == quote as char => return (scalar, offset + n + 2),
+                        _ => scalar.push(ch),
+                    }
+                }
+
+                (scalar, text.len())
+            }
+            _ => {
+                let end = text.as_bytes()[offset..]
+                    .iter()
+                    .position(|byte| stops.contains(byte))
+                    .map_or(text.len(), |n| offset + n);
+
+                (text[offset..end].trim().to_string(), end)
+            }
+        }
+    }
+
+    // block mappings and sequences, by their indentation, with any
+    // flow collections in them scanned as json is
+    fn yaml_span(text: &str) -> Span {
+        let mut lines = Vec::new();
+        let mut offset = 0;
+
+        for line in text.split_inclusive('\n') {
+            let content = line.trim_start_matches(' ');
+            let column = line.len() - content.len();
+            let content = content.trim_end();
+
+            if !content.is_empty() && !content.starts_with('#') && content != "---" {
+                lines.push((offset + column, column, content))
+            }
+
+            offset += line.len();
+        }
+
+        Self::yaml_block(text, &mut lines, &mut 0)
+    }
+
+    // the node starting at the line at, and the lines after it that
+    // belong to it. lines are their offset, column and content
+    fn yaml_block(text: &str, lines: &mut [(usize, usize, &str)], at: &mut usize) -> Span {
+        let (start, column, content) = match lines.get(*at) {
+            Some(line) => *line,
+            None => return Span::default(),
+        };
+        let mut span = Span {
+            start,
+            ..Default::default()
+        };
+        let is_item = |content: &str| content == "-" || content.starts_with("- ");
+
+        if content.starts_with(['[', '{']) {
+            let (flow, end) = Self::flow_span(text, start);
+
+            while lines.get(*at).is_some_and(|line| line.0 < end) {
+                *at += 1
+            }
+            return flow;
+        }
+
+        if is_item(content) {
+            while let Some(&(offset, item_column, content)) = lines.get(*at) {
+                if item_column != column || !is_item(content) {
+                    break;
+                }
+
+                // the item's content is scanned as a line of its own
+                let item = content[1..].trim_start();
+                let indent = content.len() - item.len();
+
+                let item = match item.is_empty() {
+                    true => {
+                        *at += 1;
+                        match lines.get(*at) {
+                            Some(line) if line.1 > column => Self::yaml_block(text, lines, at),
+                            _ => Span::default(),
+                        }
+                    }
+                    false => {
+                        lines[*at] = (offset + indent, column + indent, item);
+                        Self::yaml_block(text, lines, at)
+                    }
+                };
+
+                span.items.push(Span {
+                    start: offset,
+                    ..item
+                })
+            }
+
+            return span;
+        }
+
+        if Self::yaml_key(content).is_none() {
+            *at += 1;
+            while lines.get(*at).is_some_and(|line| line.1 > column) {
+                *at += 1
+            }
+            return span;
+        }
+
+        while let Some(&(offset, key_column, content)) = lines.get(*at) {
+            let (key, rest) = match Self::yaml_key(content) {
+                Some(key) if key_column == column && !is_item(content) => key,
+                _ => break,
+            };
+            let rest_offset = offset + content.len() - rest.len();
+            let rest = rest.trim_start();
+
+            *at += 1;
+
+            let value = if rest.is_empty() || rest.starts_with('#') {
+                match lines.get(*at) {
+                    Some(line) if line.1 > column || (line.1 == column && is_item(line.2)) => {
+                        Self::yaml_block(text, lines, at)
+                    }
+                    _ => Span::default(),
+                }
+            } else if rest.starts_with(['[', '{']) {
+                let (flow, end) = Self::flow_span(text, rest_offset);
+
+                while lines.get(*at).is_some_and(|line| line.0 < end) {
+                    *at += 1
+                }
+                flow
+            } else {
+                // a scalar, and any lines continuing it
+                while lines.get(*at).is_some_and(|line| line.1 > column) {
+                    *at += 1
+                }
+                Span::default()
+            };
+
+            span.keys.push((
+                key,
+                Span {
+                    start: offset,
+                    ..value
+                },
+            ))
+        }
+
+        span
+    }
+
+    // a mapping line's key, and the rest of the line after its colon
+    fn yaml_key(content: &str) -> Option<(String, &str)> {
+        if let Some(quote) = content
+            .chars()
+            .next()
+            .filter(|ch| *ch == '"' || *ch == '\'')
+        {
+            let end = content[1..].find(quote)? + 1;
+            let rest = content[end + 1..].trim_start().strip_prefix(':')?;
+
+            return Some((content[1..end].to_string(), rest));
+        }
+
+        let colon = content
+            .match_indices(':')
+            .map(|(n, _)| n)
+            .find(|n| content[n + 1..].is_empty() || content[n + 1..].starts_with([' ', '\t']))?;
+
+        Some((
+            content[..colon].trim_end().to_string(),
+            &content[colon + 1..],
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the line, column and message of each diagnostic for text
+    // in a hosts file with the extension
+    fn diagnostics(extension: &str, text: &str) -> Vec<(usize, usize, String)> {
+        let path = std::env::temp_dir().join(format!("rsnitch-test-validate.{}", extension));
+
+        std::fs::write(&path, text).unwrap();
+        let validation = Validation::validate(&path, false);
+        std::fs::remove_file(&path).ok();
+
+        validation
+            .diagnostics
+            .into_iter()
+            .map(|diagnostic| (diagnostic.line, diagnostic.column, diagnostic.message))
+            .collect()
+    }
+
+    fn at(line: usize, column: usize, message: &str) -> (usize, usize, String) {
+        (line, column, message.to_string())
+    }
+
+    #[test]
+    fn json_spans() {
+        let text = r#"[
+  { "group": "g", "host": "a", "label": "a",
+    "probes": [ { "type": "tcp", "port": 22 }, { "type": "zzz" } ] },
+  { "group": "g", "host": "b", "label": "b", "extra": true }
+]
+"#;
+
+        assert_eq!(
+            diagnostics("json", text),
+            [
+                at(3, 48, "unknown probe type `zzz`"),
+                at(4, 46, "unknown field `extra`")
+            ]
+        );
+    }
+
+    #[test]
+    fn yaml_spans() {
+        let text = "hosts:
+- group: g
+  host: a
+  label: a
+  probes:
+    - type: tcp
+      port: 22
+    - type: zzz
+- group: g
+  host: b
+  label: b
+  extra: true
+  probes: [ {type: tcp, port: 1}, {type: zzz} ]
+";
+
+        assert_eq!(
+            diagnostics("yaml", text),
+            [
+                at(8, 5, "unknown probe type `zzz`"),
+                at(12, 3, "unknown field `extra`"),
+                at(13, 35, "unknown probe type `zzz`")
+            ]
+        );
+    }
+
+    #[test]
+    fn toml_spans() {
+        let text = r#"[[hosts]]
+group = "g"
+host = "a"
+label = "a"
+
+[[hosts.probes]]
+type = "tcp"
+port = 22
+
+[[hosts.probes]]
+type = "zzz"
+
+[[hosts]]
+group = "g"
+host = "b"
+label = "b"
+extra = true
+"#;
+
+        assert_eq!(
+            diagnostics("toml", text),
+            [
+                // an array of tables' items start at their headers
+                at(10, 1, "unknown probe type `zzz`"),
+                at(17, 1, "unknown field `extra`")
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        let text = "[\n  { \"host\": \"a\" },\n  { \"host\" \"b\" }\n]\n";
+        let diagnostics = diagnostics("json", text);
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!((diagnostics[0].0, diagnostics[0].1), (3, 12));
+    }
+}
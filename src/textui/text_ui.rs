@@ -0,0 +1,646 @@
+//  SPDX-FileCopyrightText: Copyright 2023 James M. Putnam (putnamjm.design@gmail.com)
+//  SPDX-License-Identifier: MIT
+
+// rsnitch terminal ui
+//
+// the same layout as the iced SnitchUi, an info box and group
+// tree beside the host grid, and a status bar, drawn with ratatui
+use {
+    crate::{
+        settings::{Keys, StateColors, Traceroute},
+        snitch::{
+            alert::Alerts,
+            group::GroupTree,
+            history::History,
+            host::{Host, Poll, State},
+            traceroute::Trace,
+        },
+        Environment,
+    },
+    crossterm::{
+        cursor,
+        event::{self, Event, KeyCode, KeyEventKind},
+        execute,
+        terminal::{self, EnterAlternateScreen, LeaveAlternateScreen},
+    },
+    ratatui::{
+        backend::CrosstermBackend,
+        layout::{Constraint, Direction, Layout, Rect},
+        style::{Color, Modifier, Style},
+        text::{Line, Span},
+        widgets::{Block, Borders, Paragraph},
+        Frame, Terminal,
+    },
+    std::{
+        io,
+        str::FromStr,
+        sync::{
+            mpsc::{self, Receiver, TryRecvError},
+            Arc,
+        },
+        thread,
+        time::Duration,
+    },
+};
+
+// leaves raw mode and the alternate screen when dropped, however run
+// returns, and before a panic's message is printed
+struct Restore;
+
+impl Restore {
+    fn new() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+
+        let hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            Self::restore();
+            hook(info)
+        }));
+
+        let restore = Restore;
+
+        execute!(io::stdout(), EnterAlternateScreen)?;
+        Ok(restore)
+    }
+
+    fn restore() {
+        terminal::disable_raw_mode().ok();
+        execute!(io::stdout(), LeaveAlternateScreen, cursor::Show).ok();
+    }
+}
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        Self::restore()
+    }
+}
+
+pub struct TextUi {
+    alerts: Alerts,
+    colors: [Color; 5],
+    cursor: Option<usize>,
+    filter: String,
+    groups: GroupTree,
+    // the first lines shown in the group tree and host grid, which
+    // follow the selected group and the cursor
+    group_scroll: usize,
+    history: History,
+    host_path: String,
+    host_scroll: usize,
+    hosts: Vec<Host>,
+    info: Vec<String>,
+    keys: Keys,
+    last_poll: Option<time::OffsetDateTime>,
+    poll: Arc<Poll>,
+    poll_interval_secs: u64,
+    polling: Option<Receiver<Vec<State>>>,
+    search: String,
+    searching: bool,
+    states: Vec<State>,
+    // shown in the info box, from its last lines, while it's set
+    trace: Option<Trace>,
+    traceroute: Traceroute,
+}
+
+impl TextUi {
+    const POLL_INTERVAL: u64 = 180; // sntop uses 180 seconds by default
+    const COLS: usize = 5;
+    const INFO_ROWS: u16 = 6;
+    const QUIT: &str = "q";
+    const SILENCE_MINUTES: i64 = 60;
+
+    pub fn new(env: &Environment) -> io::Result<Self> {
+        let (hosts, info) = match Host::load(env) {
+            Ok(hosts) => (hosts, Vec::new()),
+            Err(e) => (Vec::new(), vec![e]),
+        };
+        let groups = GroupTree::new(&hosts);
+        let poll = Arc::new(
+            Poll::new(env).map_err(|e| io::Error::new(io::ErrorKind::PermissionDenied, e))?,
+        );
+        let states = vec![State::Unknown; hosts.len()];
+
+        let (keys, colors, traceroute) = match &env.settings {
+            Some(settings) => (
+                settings.keys.clone(),
+                settings.theme.states.clone(),
+                settings.traceroute.clone(),
+            ),
+            None => (
+                Keys::default(),
+                StateColors::default(),
+                Traceroute::default(),
+            ),
+        };
+
+        let color = |hex: &str| Color::from_str(hex).unwrap_or(Color::Gray);
+
+        let mut text_ui = TextUi {
+            alerts: Alerts::new(env),
+            colors: [
+                color(&colors.up),
+                color(&colors.degraded),
+                color(&colors.down),
+                color(&colors.unreachable),
+                color(&colors.unknown),
+            ],
+            cursor: None,
+            filter: env.group.clone().unwrap_or_default(),
+            groups,
+            group_scroll: 0,
+            history: History::new(env),
+            host_path: Host::path(env).display().to_string(),
+            host_scroll: 0,
+            hosts,
+            info,
+            keys,
+            last_poll: None,
+            poll,
+            poll_interval_secs: env.interval.unwrap_or(Self::POLL_INTERVAL),
+            polling: None,
+            search: String::new(),
+            searching: false,
+            states,
+            trace: None,
+            traceroute,
+        };
+
+        text_ui.poll_hosts();
+        Ok(text_ui)
+    }
+
+    pub fn run(env: &Environment) -> io::Result<()> {
+        let mut text_ui = TextUi::new(env)?;
+
+        let _restore = Restore::new()?;
+        let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+
+        text_ui.event_loop(&mut terminal)
+    }
+
+    fn now() -> time::OffsetDateTime {
+        time::OffsetDateTime::now_local().unwrap_or_else(|_| time::OffsetDateTime::now_utc())
+    }
+
+    // poll in a thread, so ping bursts and slow probes don't hold up
+    // the keys and the redraw. a poll already under way isn't started
+    // again
+    fn poll_hosts(&mut self) {
+        if self.polling.is_some() {
+            return;
+        }
+
+        let poll = self.poll.clone();
+        let hosts = self.hosts.clone();
+        let (sender, receiver) = mpsc::channel();
+
+        thread::spawn(move || sender.send(poll.poll_all(&hosts)).ok());
+        self.polling = Some(receiver);
+    }
+
+    // take the states of a poll that's done, if any
+    fn polled(&mut self) {
+        let states = match self.polling.as_ref().map(|polling| polling.try_recv()) {
+            Some(Ok(states)) => states,
+            Some(Err(TryRecvError::Disconnected)) => {
+                self.polling = None;
+                self.last_poll = Some(Self::now());
+                return;
+            }
+            _ => return,
+        };
+
+        self.polling = None;
+        self.history.record(&self.hosts, &self.states, &states).ok();
+        self.alerts.fire(
+            &self.hosts,
+            &self.states,
+            &states,
+            &self.poll.quiet(&self.hosts, &states),
+        );
+        self.states = states;
+        self.last_poll = Some(Self::now());
+    }
+
+    fn next_poll_secs(&self) -> u64 {
+        match &self.last_poll {
+            Some(last_poll) => {
+                let elapsed = (Self::now() - *last_poll).whole_seconds().max(0) as u64;
+
+                self.poll_interval_secs.saturating_sub(elapsed)
+            }
+            None => 0,
+        }
+    }
+
+    fn visible(&self) -> Vec<usize> {
+        self.hosts
+            .iter()
+            .enumerate()
+            .filter(|(_, host)| host.matches(&self.filter, &self.search))
+            .map(|(id, _)| id)
+            .collect()
+    }
+
+    fn color(&self, state: State) -> Color {
+        match state {
+            State::Up => self.colors[0],
+            State::Degraded => self.colors[1],
+            State::Down => self.colors[2],
+            State::Unreachable => self.colors[3],
+            State::Unknown => self.colors[4],
+        }
+    }
+
+    fn icon(state: State) -> &'static str {
+        match state {
+            State::Up => "\u{2713}",
+            State::Degraded => "!",
+            State::Down => "x",
+            State::Unreachable => "-",
+            State::Unknown => "?",
+        }
+    }
+
+    fn event_loop(
+        &mut self,
+        terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    ) -> io::Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+
+            if event::poll(Duration::from_millis(1000))? {
+                if let Event::Key(key) = event::read()? {
+                    if key.kind == KeyEventKind::Press && !self.key_press(key.code) {
+                        return Ok(());
+                    }
+                }
+            }
+
+            if let Some(trace) = &mut self.trace {
+                trace.drain();
+                self.info = trace.lines.clone()
+            }
+
+            self.polled();
+            if self.next_poll_secs() == 0 {
+                self.poll_hosts()
+            }
+        }
+    }
+
+    // key names as used in the settings key bindings
+    fn key_name(key_code: KeyCode) -> String {
+        match key_code {
+            KeyCode::Char(ch) => ch.to_string(),
+            KeyCode::Up => "up".to_string(),
+            KeyCode::Down => "down".to_string(),
+            KeyCode::Left => "left".to_string(),
+            KeyCode::Right => "right".to_string(),
+            KeyCode::Enter => "enter".to_string(),
+            KeyCode::Esc => "escape".to_string(),
+            _ => String::new(),
+        }
+    }
+
+    // false when it's time to quit
+    fn key_press(&mut self, key_code: KeyCode) -> bool {
+        if self.searching {
+            match key_code {
+                KeyCode::Char(ch) => self.search.push(ch),
+                KeyCode::Backspace => {
+                    self.search.pop();
+                }
+                KeyCode::Enter | KeyCode::Esc => self.searching = false,
+                _ => (),
+            }
+            self.cursor = None;
+
+            return true;
+        }
+
+        let key = Self::key_name(key_code);
+        let visible = self.visible();
+        let steps = [
+            (&self.keys.left, (-1, 0)),
+            (&self.keys.right, (1, 0)),
+            (&self.keys.up, (0, -1)),
+            (&self.keys.down, (0, 1)),
+        ];
+        let step = steps
+            .iter()
+            .find(|(name, _)| **name == key)
+            .map(|(_, step)| *step);
+
+        if let Some((dx, dy)) = step {
+            self.cursor = match self.cursor {
+                _ if visible.is_empty() => None,
+                Some(cursor) => {
+                    let cursor = cursor as isize + dx + dy * Self::COLS as isize;
+
+                    Some(cursor.clamp(0, visible.len() as isize - 1) as usize)
+                }
+                None => Some(0),
+            }
+        } else if key == self.keys.trace {
+            if let Some(id) = self.cursor.and_then(|cursor| visible.get(cursor)) {
+                let host = &self.hosts[*id];
+
+                self.trace = Some(Trace::new(
+                    &host.host,
+                    self.poll.dns.resolve(&host.host),
+                    &self.traceroute,
+                ))
+            }
+        } else if key == self.keys.details {
+            self.trace = None;
+            if let Some(id) = self.cursor.and_then(|cursor| visible.get(cursor)) {
+                self.info = Host::details(&self.poll, &self.hosts[*id], self.states[*id])
+            }
+        } else if key == self.keys.refresh {
+            self.poll_hosts()
+        } else if key == self.keys.silence {
+            // toggles an hour's silence
+            self.trace = None;
+            if let Some(id) = self.cursor.and_then(|cursor| visible.get(cursor)) {
+                let host = &self.hosts[*id];
+                let minutes = match self.poll.schedule.silenced_until(host) {
+                    Some(_) => 0,
+                    None => Self::SILENCE_MINUTES,
+                };
+
+                self.info = match self.poll.schedule.silence(host, minutes) {
+                    Ok(()) => Host::details(&self.poll, host, self.states[*id]),
+                    Err(e) => vec![format!("silences: {}", e)],
+                }
+            }
+        } else if key == self.keys.search {
+            self.searching = true
+        } else if key == self.keys.clear {
+            self.filter = String::new();
+            self.search = String::new();
+            self.cursor = None;
+        } else if key == Self::QUIT {
+            return false;
+        } else if let Some(n) = self.keys.groups.iter().position(|group| group == &key) {
+            // selecting the group again expands or collapses it
+            if let Some(id) = self.groups.visible().get(n) {
+                let path = &self.groups.group(*id).path;
+
+                if self.filter == *path {
+                    self.groups.toggle(*id)
+                } else {
+                    self.filter = path.clone();
+                    self.cursor = None;
+                }
+            }
+        }
+
+        true
+    }
+
+    // the first line to show so that line is among the height shown
+    fn follow(scroll: usize, line: usize, height: usize) -> usize {
+        if line < scroll {
+            line
+        } else if line >= scroll + height {
+            line + 1 - height
+        } else {
+            scroll
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let frame_rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(1),
+                Constraint::Min(0),
+                Constraint::Length(2),
+            ])
+            .split(frame.size());
+
+        let down = State::count(&self.states, State::Down);
+        let title = match down {
+            0 => String::from("rsnitch 0.0.2"),
+            down => format!("rsnitch 0.0.2 - {} down", down),
+        };
+
+        frame.render_widget(
+            Paragraph::new(title).style(Style::default().add_modifier(Modifier::BOLD)),
+            frame_rows[0],
+        );
+
+        let hosts_frame = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Length(30), Constraint::Min(0)])
+            .split(frame_rows[1]);
+
+        // the info box grows with host details, up to half the column
+        let info_rows = (self.info.len() as u16)
+            .max(Self::INFO_ROWS)
+            .min(hosts_frame[0].height / 2);
+        let info_col = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(info_rows + 2), Constraint::Min(0)])
+            .split(hosts_frame[0]);
+
+        frame.render_widget(
+            Paragraph::new(
+                self.info
+                    .iter()
+                    .map(|line| Line::from(line.clone()))
+                    .collect::<Vec<Line>>(),
+            )
+            .block(Block::default().borders(Borders::ALL).title("info"))
+            .scroll(match self.trace {
+                Some(_) => (self.info.len().saturating_sub(info_rows as usize) as u16, 0),
+                None => (0, 0),
+            }),
+            info_col[0],
+        );
+
+        // each row of hosts is followed by a blank line
+        self.host_scroll = match self.cursor {
+            Some(cursor) => Self::follow(
+                self.host_scroll,
+                cursor / Self::COLS * 2,
+                hosts_frame[1].height.saturating_sub(2).max(1) as usize,
+            ),
+            None => 0,
+        };
+
+        let groups = self.groups.visible();
+        self.group_scroll = match groups
+            .iter()
+            .position(|id| self.groups.group(*id).path == self.filter)
+        {
+            Some(line) => Self::follow(
+                self.group_scroll,
+                line,
+                info_col[1].height.saturating_sub(2).max(1) as usize,
+            ),
+            None => self.group_scroll.min(groups.len().saturating_sub(1)),
+        };
+
+        frame.render_widget(self.group_view(), info_col[1]);
+        frame.render_widget(self.host_view(hosts_frame[1]), hosts_frame[1]);
+        frame.render_widget(self.status_view(), frame_rows[2]);
+    }
+
+    fn group_view(&self) -> Paragraph {
+        let lines: Vec<Line> = self
+            .groups
+            .visible()
+            .iter()
+            .enumerate()
+            .map(|(n, id)| {
+                let group = self.groups.group(*id);
+                let (up, down) = self.groups.counts(*id, &self.states);
+
+                let toggle = if group.children == 0 {
+                    " "
+                } else if self.groups.is_expanded(*id) {
+                    "-"
+                } else {
+                    "+"
+                };
+
+                let key = match self.keys.groups.get(n) {
+                    Some(key) => key.as_str(),
+                    None => " ",
+                };
+
+                let style = if group.path == self.filter {
+                    Style::default().add_modifier(Modifier::REVERSED)
+                } else {
+                    Style::default()
+                };
+
+                Line::from(Span::styled(
+                    format!(
+                        "{} {}{} {} {}/{}",
+                        key,
+                        " ".repeat(group.depth * 2),
+                        toggle,
+                        group.name,
+                        up,
+                        down
+                    ),
+                    style,
+                ))
+            })
+            .collect();
+
+        Paragraph::new(lines)
+            .block(Block::default().borders(Borders::ALL).title("groups"))
+            .scroll((self.group_scroll as u16, 0))
+    }
+
+    fn host_view(&self, area: Rect) -> Paragraph {
+        let width = (area.width.saturating_sub(2) as usize / Self::COLS).max(4);
+        let visible = self.visible();
+        let notes = self.poll.notes(&self.hosts, &self.states);
+        let flapping = self.poll.flap.flapping(&self.hosts);
+
+        let lines: Vec<Line> = visible
+            .chunks(Self::COLS)
+            .enumerate()
+            .map(|(row, ids)| {
+                let spans: Vec<Span> = ids
+                    .iter()
+                    .enumerate()
+                    .map(|(col, id)| {
+                        let state = self.states[*id];
+                        let mut style = Style::default().fg(Color::Black).bg(self.color(state));
+
+                        if self.cursor == Some(row * Self::COLS + col) {
+                            style = style.add_modifier(Modifier::REVERSED)
+                        }
+
+                        if flapping[*id] {
+                            style = style.add_modifier(Modifier::ITALIC | Modifier::UNDERLINED)
+                        }
+
+                        let label = match notes[*id].is_empty() {
+                            true => self.hosts[*id].label.clone(),
+                            false => {
+                                format!("{} ({})", self.hosts[*id].label, notes[*id].join(", "))
+                            }
+                        };
+                        let label = match flapping[*id] {
+                            true => format!("{} ~", label),
+                            false => label,
+                        };
+                        let label: String = format!("{} {}", Self::icon(state), label)
+                            .chars()
+                            .take(width - 1)
+                            .collect();
+
+                        Span::styled(format!("{:<w$}", label, w = width - 1), style)
+                    })
+                    .flat_map(|span| [span, Span::raw(" ")])
+                    .collect();
+
+                Line::from(spans)
+            })
+            .flat_map(|line| [line, Line::from("")])
+            .collect();
+
+        Paragraph::new(lines)
+            .block(Block::default().borders(Borders::ALL).title("hosts"))
+            .scroll((self.host_scroll as u16, 0))
+    }
+
+    fn status_view(&self) -> Paragraph {
+        let search = if self.searching {
+            format!("search: {}_", self.search)
+        } else {
+            format!("search: {}", self.search)
+        };
+
+        let last_poll = match &self.last_poll {
+            Some(time) => format!(
+                "{:02}:{:02}:{:02}",
+                time.hour(),
+                time.minute(),
+                time.second()
+            ),
+            None => "never".to_string(),
+        };
+
+        let next_poll_secs = self.next_poll_secs();
+        let dns_failing = match self
+            .poll
+            .dns_failing(&self.hosts)
+            .iter()
+            .filter(|failing| **failing)
+            .count()
+        {
+            0 => String::new(),
+            n => format!(" / {} dns failing", n),
+        };
+
+        Paragraph::new(vec![
+            Line::from(format!(
+                "{}    filter: {}    {}",
+                self.host_path, self.filter, search
+            )),
+            Line::from(format!(
+                "{} up / {} degraded / {} down{} / {} unknown{}    last poll: {}    next poll: {}:{:02}",
+                State::count(&self.states, State::Up),
+                State::count(&self.states, State::Degraded),
+                State::count(&self.states, State::Down),
+                match State::count(&self.states, State::Unreachable) {
+                    0 => String::new(),
+                    n => format!(" / {} unreachable", n),
+                },
+                State::count(&self.states, State::Unknown),
+                dns_failing,
+                last_poll,
+                next_poll_secs / 60,
+                next_poll_secs % 60,
+            )),
+        ])
+    }
+}